@@ -0,0 +1,31 @@
+//! Captures build metadata for `BuildInfo`.
+
+use std::{env, process::Command};
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(&["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=CORYLUS_GIT_HASH={}", git_hash);
+    // a file that never exists, so that the date and hash are captured again on every build
+    println!("cargo:rerun-if-changed=.corylus-build-always");
+
+    let build_date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    println!("cargo:rustc-env=CORYLUS_BUILD_DATE={}", build_date);
+
+    let mut features = env::vars()
+        .filter_map(|(name, _)| {
+            // cargo turns `-` into `_` in the variable, the features of this crate have none
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase())
+        })
+        .filter(|feature| feature != "default")
+        .collect::<Vec<_>>();
+    features.sort();
+    println!("cargo:rustc-env=CORYLUS_FEATURES={}", features.join(","));
+}
@@ -1,160 +1,294 @@
 use amethyst::{
+    config::Config,
+    core::{transform::Parent, Time},
     ecs::prelude::Entity,
-    input::{is_close_requested, is_key_down},
+    input::is_close_requested,
     prelude::*,
-    ui::{
-        Anchor, ToNativeWidget, UiCreator, UiEvent, UiEventType, UiFinder, UiTransformData,
-        UiWidget,
-    },
-    winit::VirtualKeyCode,
+    ui::{Anchor, LineMode, Stretch, UiEvent, UiEventType, UiText, UiTransform},
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    winit::{Event, MouseScrollDelta, WindowEvent},
 };
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::{event, instrument, Level};
 
-use crate::menu::MainMenu;
+use crate::build_info::BuildInfo;
+use crate::focus::{Focusable, UiFocus};
+use crate::link::Link;
 use crate::ui_scheme::*;
+use crate::{bindings, console, router};
 
-#[derive(Debug, Default)]
-pub struct AboutScreen {
-    ui_handle: Option<Entity>,
-    twitter_link: Option<Entity>,
+// speed of the roll when scrolling by itself, in pixels per second
+const SCROLL_SPEED: f32 = 60.;
+// distance scrolled by hand with each key press or mouse wheel step
+const SCROLL_STEP: f32 = 80.;
+const ROLL_WIDTH: f32 = 1000.;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Credit {
+    pub name: String,
+    /// What they did, or the licence of an asset.
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreditSection {
+    pub title: String,
+    pub entries: Vec<Credit>,
+}
+
+/// Everyone and everything to thank, from `assets/credits.ron`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Credits {
+    pub sections: Vec<CreditSection>,
+}
+
+/// One line of the credits roll.
+struct Line {
+    text: String,
+    font_size: f32,
+    color: [f32; 4],
+    height: f32,
+    link: Option<String>,
+}
+
+impl Line {
+    fn new(text: String, font_size: f32, color: [f32; 4], height: f32) -> Self {
+        Self {
+            text,
+            font_size,
+            color,
+            height,
+            link: None,
+        }
+    }
 }
 
-#[derive(Clone, Deserialize, Debug)]
-struct About {
-    version: String,
+impl Credits {
+    fn lines(&self, build_info: &BuildInfo) -> Vec<Line> {
+        let mut lines = vec![
+            Line::new(format!("Corylus {}", build_info.version), 60., COLOR_3, 80.),
+            Line::new(
+                format!(
+                    "{} - built {} - {}",
+                    build_info.git_hash, build_info.build_date, build_info.backend
+                ),
+                24.,
+                COLOR_TEXT_LIGHT,
+                35.,
+            ),
+            Line::new(
+                format!("features: {}", build_info.features.join(", ")),
+                24.,
+                COLOR_TEXT_LIGHT,
+                75.,
+            ),
+        ];
+        for section in &self.sections {
+            lines.push(Line::new(section.title.clone(), 45., COLOR_3, 70.));
+            for credit in &section.entries {
+                lines.push(Line {
+                    link: credit.link.clone(),
+                    ..Line::new(credit.name.clone(), 40., COLOR_TEXT_LIGHT, 55.)
+                });
+                if let Some(role) = &credit.role {
+                    lines.push(Line::new(role.clone(), 28., COLOR_TEXT_LIGHT, 45.));
+                }
+            }
+            lines.push(Line::new(String::new(), 28., COLOR_3, 50.));
+        }
+        lines
+    }
 }
 
-impl ToNativeWidget for About {
-    type PrefabData = ();
+/// Scrolls the `Credits` from the bottom to the top of the window, then starts again. The roll
+/// can be paused, and scrolled by hand with the navigation actions or the mouse wheel.
+#[derive(Debug, Default)]
+pub struct AboutScreen {
+    ui_handle: Option<Entity>,
+    roll: Option<Entity>,
+    roll_height: f32,
+    // distance the roll moved up since it started below the window
+    offset: f32,
+    paused: bool,
+}
 
-    fn to_native_widget(self, _: ()) -> (UiWidget<About>, Self::PrefabData) {
-        let widget = UiWidget::Container {
-            background: None,
-            transform: UiTransformData::default()
-                .with_size(20., 20.)
-                .with_anchor(Anchor::Middle)
-                .with_stretch(amethyst::ui::Stretch::XY {
+impl AboutScreen {
+    fn create_roll(&mut self, world: &mut World, credits: &Credits) {
+        let root = world
+            .create_entity()
+            .with(
+                UiTransform::new(
+                    "about".to_string(),
+                    Anchor::Middle,
+                    Anchor::Middle,
+                    0.,
+                    0.,
+                    0.,
+                    20.,
+                    20.,
+                )
+                .with_stretch(Stretch::XY {
                     x_margin: 0.,
                     y_margin: 0.,
                     keep_aspect_ratio: false,
                 }),
-            children: vec![
-                UiWidget::Label {
-                    transform: UiTransformData::default()
-                        .with_size(480., 720.)
-                        .with_anchor(Anchor::Middle),
-                    text: amethyst::ui::UiTextData {
-                        text: format!(
-                            r###"
-Corylus {}
-
-by François Mockers
-"###,
-                            self.version
-                        ),
-                        font: Some(amethyst::assets::AssetPrefab::File(
-                            "font/mandrill.ttf".to_string(),
-                            Box::new(amethyst::ui::TtfFormat),
-                        )),
-                        font_size: 60.,
-                        color: COLOR_3,
-                        password: false,
-                        align: Some(Anchor::Middle),
-                        line_mode: Some(amethyst::ui::LineMode::Wrap),
-                        editable: None,
-                    },
-                },
-                UiWidget::Container {
-                    transform: UiTransformData::default()
-                        .with_size(700., 150.)
-                        .with_anchor(Anchor::BottomRight),
-                    background: None,
-                    children: vec![UiWidget::Label {
-                        transform: UiTransformData::default()
-                            .with_size(600., 70.)
-                            .with_anchor(Anchor::TopLeft)
-                            .with_id("twitter-link"),
-                        text: amethyst::ui::UiTextData {
-                            text: "Find me on twitter: @FrancoisMockers".to_string(),
-                            font: Some(amethyst::assets::AssetPrefab::File(
-                                "font/mandrill.ttf".to_string(),
-                                Box::new(amethyst::ui::TtfFormat),
-                            )),
-                            font_size: 40.,
-                            color: COLOR_TEXT_LIGHT,
-                            password: false,
-                            align: Some(Anchor::Middle),
-                            line_mode: Some(amethyst::ui::LineMode::Wrap),
-                            editable: None,
-                        },
-                    }],
-                },
-            ],
-        };
-        (widget, ())
+            )
+            .build();
+
+        let lines = credits.lines(&world.read_resource::<BuildInfo>());
+        self.roll_height = lines.iter().map(|line| line.height).sum();
+        let roll = world
+            .create_entity()
+            .with(UiTransform::new(
+                "credits_roll".to_string(),
+                Anchor::BottomMiddle,
+                Anchor::TopMiddle,
+                0.,
+                0.,
+                1.,
+                ROLL_WIDTH,
+                self.roll_height,
+            ))
+            .with(Parent { entity: root })
+            .build();
+
+        let font = load_font(world, "font/mandrill.ttf");
+        let mut y = 0.;
+        let mut links = 0;
+        for (i, line) in lines.into_iter().enumerate() {
+            let mut text = UiText::new(font.clone(), line.text, line.color, line.font_size);
+            text.line_mode = LineMode::Wrap;
+            let mut builder = world
+                .create_entity()
+                .with(UiTransform::new(
+                    format!("credits_line_{}", i),
+                    Anchor::TopMiddle,
+                    Anchor::TopMiddle,
+                    0.,
+                    -y,
+                    1.,
+                    ROLL_WIDTH,
+                    line.height,
+                ))
+                .with(text)
+                .with(Parent { entity: roll });
+            if let Some(url) = line.link {
+                builder = builder.with(Link::new(&url)).with(Focusable::new(links));
+                links += 1;
+            }
+            builder.build();
+            y += line.height;
+        }
+
+        self.ui_handle = Some(root);
+        self.roll = Some(roll);
+    }
+
+    /// Move the roll by `distance`, starting again from the bottom when it left the window.
+    fn scroll(&mut self, world: &World, distance: f32) {
+        let screen_height = world.read_resource::<ScreenDimensions>().height();
+        let end = screen_height + self.roll_height;
+        self.offset = (self.offset + distance).max(0.);
+        if self.offset > end {
+            self.offset = 0.;
+        }
+        if let Some(roll) = self.roll {
+            if let Some(transform) = world.write_storage::<UiTransform>().get_mut(roll) {
+                transform.local_y = self.offset;
+            }
+        }
+    }
+}
+
+fn mouse_wheel_lines(event: &Event) -> Option<f32> {
+    match event {
+        Event::WindowEvent {
+            event: WindowEvent::MouseWheel { delta, .. },
+            ..
+        } => Some(match delta {
+            MouseScrollDelta::LineDelta(_, y) => *y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / SCROLL_STEP,
+        }),
+        _ => None,
     }
 }
 
 impl SimpleState for AboutScreen {
     #[instrument(skip(data), level = "info")]
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        let world = data.world;
-        self.ui_handle = Some(
-            world.exec(|mut creator: UiCreator<'_, About>| creator.create("ui/about.ron", ())),
-        );
+        let credits = application_root_dir()
+            .map_err(amethyst::Error::from)
+            .and_then(|root| Ok(Credits::load(root.join("assets/credits.ron"))?))
+            .unwrap_or_else(|err| {
+                event!(Level::ERROR, "Error loading credits: {}", err);
+                Credits::default()
+            });
+        self.offset = 0.;
+        self.paused = false;
+        self.create_roll(data.world, &credits);
+        // up and down scroll the roll, the links are reached with the other directions
+        data.world.write_resource::<UiFocus>().horizontal = true;
     }
 
-    #[instrument(skip(state_data), level = "info")]
-    fn update(&mut self, state_data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        // only search for buttons if they have not been found yet
-        let StateData { world, .. } = state_data;
-
-        if self.twitter_link.is_none() {
-            world.exec(|ui_finder: UiFinder<'_>| {
-                self.twitter_link = ui_finder.find("twitter-link");
-            });
+    #[instrument(skip(data), level = "info")]
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if !self.paused {
+            let delta = data.world.read_resource::<Time>().delta_seconds();
+            self.scroll(data.world, SCROLL_SPEED * delta);
         }
-
         Trans::None
     }
 
-    #[instrument(skip(_data), level = "info")]
+    #[instrument(skip(data), level = "info")]
     fn handle_event(
         &mut self,
-        _data: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
-        match event {
+        match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
+            // links open themselves, any other click leaves
             StateEvent::Ui(UiEvent {
                 event_type: UiEventType::Click,
                 target,
             }) => {
-                if Some(target) == self.twitter_link {
-                    event!(Level::INFO, "Opening Twitter");
-                    if let Err(err) = webbrowser::open("https://twitter.com/FrancoisMockers") {
-                        event!(Level::WARN, "Error opening browser: {}", err);
-                    }
+                if data.world.read_storage::<Link>().contains(*target) {
                     Trans::None
                 } else {
-                    event!(Level::INFO, "Switching to MainMenu!");
-                    Trans::Switch(Box::new(MainMenu::default()))
+                    router::go(data.world, router::BACK)
                 }
             }
             StateEvent::Window(event) => {
                 if is_close_requested(&event) {
                     event!(Level::INFO, "Quitting Application!");
                     Trans::Quit
-                } else if is_key_down(&event, VirtualKeyCode::Escape)
-                // || is_mouse_button_down(&event, MouseButton::Left)
-                {
-                    event!(Level::INFO, "Switching to MainMenu!");
-                    Trans::Switch(Box::new(MainMenu::default()))
                 } else {
+                    if let Some(lines) = mouse_wheel_lines(&event) {
+                        self.scroll(data.world, -lines * SCROLL_STEP);
+                    }
                     Trans::None
                 }
             }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::BACK) => {
+                router::go(data.world, router::BACK)
+            }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::PAUSE) => {
+                self.paused = !self.paused;
+                Trans::None
+            }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::UP) => {
+                self.scroll(data.world, -SCROLL_STEP);
+                Trans::None
+            }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::DOWN) => {
+                self.scroll(data.world, SCROLL_STEP);
+                Trans::None
+            }
             _ => Trans::None,
         }
     }
@@ -168,5 +302,7 @@ impl SimpleState for AboutScreen {
         }
 
         self.ui_handle = None;
+        self.roll = None;
+        data.world.write_resource::<UiFocus>().horizontal = false;
     }
 }
@@ -0,0 +1,66 @@
+use amethyst::{
+    assets::PrefabData,
+    derive::PrefabData,
+    ecs::prelude::{Component, DenseVecStorage, Entity, World, WorldExt, WriteStorage},
+    prelude::*,
+    ui::{UiEvent, UiEventType},
+    Error,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// What happens when an UI element is activated. Set on buttons in code, or in prefabs with
+/// the `Action` custom widget.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+pub enum UiAction {
+    StartGame,
+    OpenAbout,
+    OpenHighScores,
+    OpenControls,
+    OpenOptions,
+    OpenSave,
+    OpenLoad,
+    SaveSlot(String),
+    LoadSlot(String),
+    Rebind(String),
+    ResetBindings,
+    /// Change the option with this name to its next value.
+    CycleOption(String),
+    ApplyOptions,
+    Back,
+    Resume,
+    /// Start a new run after a game over.
+    Retry,
+    /// Go on after some assets failed to load.
+    ContinueLoading,
+    ExitToMenu,
+    Quit,
+}
+
+impl Component for UiAction {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The action of the UI element that was clicked, if this event is a click on such an element.
+pub fn ui_action(world: &World, event: &StateEvent) -> Option<UiAction> {
+    match event {
+        StateEvent::Ui(UiEvent {
+            event_type: UiEventType::Click,
+            target,
+        }) => world.read_storage::<UiAction>().get(*target).cloned(),
+        _ => None,
+    }
+}
+
+/// The first UI element triggering this action.
+pub fn find_action(world: &World, action: &UiAction) -> Option<Entity> {
+    use amethyst::ecs::Join;
+
+    let entities = world.entities();
+    let actions = world.read_storage::<UiAction>();
+    (&entities, &actions)
+        .join()
+        .find(|(_, a)| *a == action)
+        .map(|(entity, _)| entity)
+}
@@ -0,0 +1,128 @@
+use amethyst::{
+    assets::AssetStorage,
+    audio::{
+        output::{init_output, Output},
+        Source, SourceHandle,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{Read, ReadStorage, System, SystemData, World, WorldExt},
+    shrev::{EventChannel, ReaderId},
+    ui::{UiEvent, UiEventType},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    settings::UserSettings,
+    ui_scheme::{ButtonComp, Theme},
+    window_focus::WindowFocus,
+};
+
+/// Volume settings, defaults from `config/audio.ron`. All volumes are between 0 and 1.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            sfx_volume: 0.8,
+        }
+    }
+}
+
+impl AudioConfig {
+    pub fn effective_sfx_volume(&self) -> f32 {
+        (self.master_volume * self.sfx_volume).max(0.).min(1.)
+    }
+}
+
+/// Sounds played when interacting with buttons, loaded from the current `Theme`.
+#[derive(Debug, Default)]
+pub struct UiSounds {
+    pub hover: Option<SourceHandle>,
+    pub click: Option<SourceHandle>,
+}
+
+impl UiSounds {
+    pub fn from_theme(world: &World, theme: &Theme) -> Self {
+        Self {
+            hover: theme
+                .sounds
+                .hover
+                .as_ref()
+                .map(|path| crate::ui_scheme::load_sound(world, path)),
+            click: theme
+                .sounds
+                .click
+                .as_ref()
+                .map(|path| crate::ui_scheme::load_sound(world, path)),
+        }
+    }
+}
+
+/// Register the audio output and load the sounds of the current `Theme`, done once at startup
+/// so that menus have sound.
+pub fn init(world: &mut World) {
+    init_output(world);
+    let sounds = {
+        let theme = world.read_resource::<Theme>();
+        UiSounds::from_theme(world, &theme)
+    };
+    world.insert(sounds);
+}
+
+/// Plays the theme sounds when hovering or clicking a button.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(UiSoundSystemDesc))]
+pub struct UiSoundSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<UiEvent>,
+}
+
+impl UiSoundSystem {
+    pub fn new(reader_id: ReaderId<UiEvent>) -> Self {
+        Self { reader_id }
+    }
+}
+
+impl<'a> System<'a> for UiSoundSystem {
+    type SystemData = (
+        Read<'a, EventChannel<UiEvent>>,
+        ReadStorage<'a, ButtonComp>,
+        Read<'a, UiSounds>,
+        Read<'a, UserSettings>,
+        Read<'a, WindowFocus>,
+        Read<'a, AssetStorage<Source>>,
+        Option<Read<'a, Output>>,
+    );
+
+    #[instrument(
+        skip(events, buttons, sounds, settings, focus, storage, output),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (events, buttons, sounds, settings, focus, storage, output): Self::SystemData,
+    ) {
+        for ev in events.read(&mut self.reader_id) {
+            if buttons.get(ev.target).is_none() || focus.muted {
+                continue;
+            }
+            let sound = match ev.event_type {
+                UiEventType::HoverStart => sounds.hover.as_ref(),
+                UiEventType::Click => sounds.click.as_ref(),
+                _ => None,
+            };
+            if let (Some(sound), Some(output)) = (sound.and_then(|h| storage.get(h)), &output) {
+                event!(Level::TRACE, "playing sound for {:?}", ev.event_type);
+                output.play_once(sound, settings.audio.effective_sfx_volume());
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::path::{Path, PathBuf};
+
+use amethyst::{
+    config::Config,
+    input::{Bindings, Button, InputEvent, StringBindings},
+    prelude::*,
+};
+
+use tracing::{event, Level};
+
+// Named actions, defined in `config/bindings.ron`.
+pub const PAUSE: &str = "pause";
+pub const BACK: &str = "back";
+pub const CONFIRM: &str = "confirm";
+pub const UP: &str = "up";
+pub const DOWN: &str = "down";
+pub const LEFT: &str = "left";
+pub const RIGHT: &str = "right";
+pub const NEXT: &str = "next";
+pub const ZOOM_IN: &str = "zoom_in";
+pub const ZOOM_OUT: &str = "zoom_out";
+pub const DEBUG_OVERLAY: &str = "debug_overlay";
+pub const CONSOLE: &str = "console";
+pub const SCREENSHOT: &str = "screenshot";
+pub const SCREENSHOT_WITHOUT_UI: &str = "screenshot_without_ui";
+// only in debug builds
+pub const SLOW_MOTION: &str = "slow_motion";
+pub const FRAME_STEP: &str = "frame_step";
+pub const STEP: &str = "step";
+
+/// Controllers that can be used at the same time, all of them get the bindings of the first one.
+pub const CONTROLLERS: u32 = 4;
+
+/// Actions that can be rebound from the controls screen, with their label.
+pub const REBINDABLE: &[(&str, &str)] = &[
+    (PAUSE, "Pause"),
+    (BACK, "Back"),
+    (CONFIRM, "Confirm"),
+    (UP, "Up"),
+    (DOWN, "Down"),
+    (LEFT, "Left"),
+    (RIGHT, "Right"),
+    (NEXT, "Next"),
+    (ZOOM_IN, "Zoom in"),
+    (ZOOM_OUT, "Zoom out"),
+];
+
+/// Is this event the action `name` being pressed.
+pub fn is_action(event: &StateEvent, name: &str) -> bool {
+    match event {
+        StateEvent::Input(InputEvent::ActionPressed(action)) => action == name,
+        _ => false,
+    }
+}
+
+fn default_bindings_path(app_root: &Path) -> PathBuf {
+    app_root.join("config/bindings.ron")
+}
+
+fn user_bindings_path() -> Option<PathBuf> {
+    crate::paths::user_config_dir().map(|dir| dir.join("bindings.ron"))
+}
+
+/// Bindings from the player config if they saved some, otherwise the defaults from
+/// `config/bindings.ron`. Actions added to the defaults since the player saved their bindings
+/// get their default bindings.
+pub fn load(app_root: &Path) -> amethyst::Result<Bindings<StringBindings>> {
+    let mut defaults = Bindings::load(default_bindings_path(app_root))?;
+    add_other_controllers(&mut defaults);
+    if let Some(path) = user_bindings_path().filter(|path| path.exists()) {
+        match Bindings::<StringBindings>::load(&path) {
+            Ok(mut bindings) => {
+                add_missing_actions(&mut bindings, &defaults);
+                add_other_controllers(&mut bindings);
+                return Ok(bindings);
+            }
+            Err(err) => event!(
+                Level::WARN,
+                "Error loading bindings from {}, using defaults: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+    Ok(defaults)
+}
+
+fn add_missing_actions(
+    bindings: &mut Bindings<StringBindings>,
+    defaults: &Bindings<StringBindings>,
+) {
+    let missing = defaults
+        .actions()
+        .filter(|action| bindings.action_bindings(*action).next().is_none())
+        .cloned()
+        .collect::<Vec<_>>();
+    for action in missing {
+        for combo in defaults.action_bindings(&action) {
+            if let Err(err) = bindings.insert_action_binding(action.clone(), combo.iter().cloned())
+            {
+                event!(
+                    Level::WARN,
+                    "Error adding default binding for {}: {:?}",
+                    action,
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Give the bindings of the first controller to the other ones.
+fn add_other_controllers(bindings: &mut Bindings<StringBindings>) {
+    let first_controller = bindings
+        .actions()
+        .flat_map(|action| {
+            bindings
+                .action_bindings(action)
+                .filter(|combo| {
+                    combo
+                        .iter()
+                        .all(|button| matches!(button, Button::Controller(0, _)))
+                })
+                .map(move |combo| (action.clone(), combo.to_vec()))
+        })
+        .collect::<Vec<_>>();
+    for (action, combo) in first_controller {
+        for which in 1..CONTROLLERS {
+            let other = combo
+                .iter()
+                .map(|button| match button {
+                    Button::Controller(_, button) => Button::Controller(which, *button),
+                    other => other.clone(),
+                })
+                .collect::<Vec<_>>();
+            if !bindings
+                .action_bindings(&action)
+                .any(|combo| combo == &other[..])
+            {
+                let _ = bindings.insert_action_binding(action.clone(), other);
+            }
+        }
+    }
+}
+
+pub fn load_defaults() -> amethyst::Result<Bindings<StringBindings>> {
+    let mut defaults = Bindings::load(default_bindings_path(
+        &amethyst::utils::application_root_dir()?,
+    ))?;
+    add_other_controllers(&mut defaults);
+    Ok(defaults)
+}
+
+/// Save the bindings in the player config.
+pub fn save(bindings: &Bindings<StringBindings>) -> amethyst::Result<()> {
+    let path = user_bindings_path()
+        .ok_or_else(|| amethyst::Error::from_string("no user config directory"))?;
+    let content = ron::ser::to_string_pretty(bindings, Default::default())?;
+    crate::paths::write_atomic(&path, content.as_bytes())?;
+    event!(Level::INFO, "Saved bindings to {}", path.display());
+    Ok(())
+}
+
+/// Another action already using this exact combination of buttons.
+pub fn conflict(
+    bindings: &Bindings<StringBindings>,
+    action: &str,
+    combo: &[Button],
+) -> Option<String> {
+    bindings
+        .actions()
+        .filter(|other| other.as_str() != action)
+        .find(|other| bindings.action_bindings(*other).any(|b| b == combo))
+        .cloned()
+}
+
+fn same_device(a: &Button, b: &Button) -> bool {
+    match (a, b) {
+        (Button::Key(_), Button::Key(_)) | (Button::Mouse(_), Button::Mouse(_)) => true,
+        (Button::Controller(_, _), Button::Controller(_, _)) => true,
+        _ => false,
+    }
+}
+
+/// Is `button` alone bound to `action`.
+pub fn is_bound(bindings: &Bindings<StringBindings>, action: &str, button: &Button) -> bool {
+    bindings
+        .action_bindings(action)
+        .any(|combo| combo == std::slice::from_ref(button))
+}
+
+/// Replace the bindings of `action` on the device of `button` (the keyboard, or the
+/// controllers) by `button`. Bindings on other devices are kept.
+pub fn rebind(
+    bindings: &mut Bindings<StringBindings>,
+    action: &str,
+    button: Button,
+) -> Result<(), String> {
+    // a controller button is bound for all controllers
+    let button = match button {
+        Button::Controller(_, button) => Button::Controller(0, button),
+        other => other,
+    };
+    let combo = [button];
+    if let Some(other) = conflict(bindings, action, &combo) {
+        return Err(other);
+    }
+    let same_device = bindings
+        .action_bindings(action)
+        .filter(|old| old.iter().all(|old| same_device(old, &combo[0])))
+        .map(|old| old.to_vec())
+        .collect::<Vec<_>>();
+    for old in same_device {
+        bindings.remove_action_binding(action, &old);
+    }
+    bindings
+        .insert_action_binding(action.to_string(), combo.iter().cloned())
+        .map_err(|err| format!("{:?}", err))?;
+    add_other_controllers(bindings);
+    Ok(())
+}
+
+pub fn describe_button(button: &Button) -> String {
+    match button {
+        Button::Key(key) => format!("{:?}", key),
+        Button::Controller(_, button) => format!("Pad {:?}", button),
+        Button::Mouse(button) => format!("Mouse {:?}", button),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Human readable bindings of an action, like "Escape / Back".
+pub fn describe(bindings: &Bindings<StringBindings>, action: &str) -> String {
+    bindings
+        .action_bindings(action)
+        .map(|combo| {
+            combo
+                .iter()
+                .map(describe_button)
+                .collect::<Vec<_>>()
+                .join("+")
+        })
+        .fold(Vec::<String>::new(), |mut described, combo| {
+            // the same for each controller
+            if !described.contains(&combo) {
+                described.push(combo);
+            }
+            described
+        })
+        .join(" / ")
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+/// How this binary was built, captured by `build.rs`.
+#[derive(Debug, Clone)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Short hash of the git commit, `unknown` when built outside of a repository.
+    pub git_hash: &'static str,
+    pub build_date: &'static str,
+    /// Enabled cargo features, without `default`.
+    pub features: Vec<&'static str>,
+    pub backend: &'static str,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("CORYLUS_GIT_HASH"),
+            build_date: env!("CORYLUS_BUILD_DATE"),
+            features: env!("CORYLUS_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+            backend: backend(),
+        }
+    }
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, built {}, {} backend, features: {})",
+            self.version,
+            self.git_hash,
+            self.build_date,
+            self.backend,
+            self.features.join(", ")
+        )
+    }
+}
+
+// same order of preference as amethyst's `DefaultBackend`
+fn backend() -> &'static str {
+    if cfg!(feature = "metal") {
+        "Metal"
+    } else if cfg!(feature = "vulkan") {
+        "Vulkan"
+    } else if cfg!(feature = "empty") {
+        "Empty"
+    } else {
+        "unknown"
+    }
+}
@@ -0,0 +1,221 @@
+use amethyst::{
+    core::{Time, Transform},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entity, Join, NullStorage, Read, ReadExpect, ReadStorage,
+        System, World, WorldExt, WriteStorage,
+    },
+    prelude::Builder,
+    renderer::Camera,
+    window::ScreenDimensions,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+// the camera looks down from above everything drawn
+const CAMERA_Z: f32 = 10.;
+const Z_NEAR: f32 = 0.125;
+const Z_FAR: f32 = 2000.;
+
+/// Area of the world, between two corners.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Bounds {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl Bounds {
+    /// Center closest to `center` for a view of size `view` to stay within the bounds. A view
+    /// larger than the bounds is centered on them.
+    fn clamp(&self, center: (f32, f32), view: (f32, f32)) -> (f32, f32) {
+        fn clamp_axis(value: f32, min: f32, max: f32, size: f32) -> f32 {
+            if max - min <= size {
+                (min + max) / 2.
+            } else {
+                value.max(min + size / 2.).min(max - size / 2.)
+            }
+        }
+        (
+            clamp_axis(center.0, self.min.0, self.max.0, view.0),
+            clamp_axis(center.1, self.min.1, self.max.1, view.1),
+        )
+    }
+}
+
+/// How the camera of the game behaves, from `config/camera.ron`. Distances are in world units.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// Height of the world shown at zoom 1, whatever the size of the window.
+    pub view_height: f32,
+    /// How fast the camera catches up with its target, 0 to stay exactly on it.
+    pub smoothing: f32,
+    pub zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// Zoom factor of each zoom action.
+    pub zoom_step: f32,
+    /// The camera never shows anything outside of them.
+    pub bounds: Option<Bounds>,
+    /// Distance the camera moves at full shake.
+    pub max_shake: f32,
+    /// Shake lost per second, full shake being 1.
+    pub shake_decay: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            view_height: 768.,
+            smoothing: 0.,
+            zoom: 1.,
+            min_zoom: 1.,
+            max_zoom: 1.,
+            zoom_step: 1.25,
+            bounds: None,
+            max_shake: 0.,
+            shake_decay: 1.,
+        }
+    }
+}
+
+/// Marks the entity the camera follows. When there are several, one of them is picked.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CameraTarget;
+
+impl Component for CameraTarget {
+    type Storage = NullStorage<Self>;
+}
+
+/// Moves the camera it is on. Its state is updated by the `CameraSystem`.
+#[derive(Debug, Clone)]
+pub struct CameraController {
+    /// Center of the view, before shaking.
+    pub position: (f32, f32),
+    pub zoom: f32,
+    /// Size of the world shown.
+    pub view: (f32, f32),
+    shake: f32,
+}
+
+impl CameraController {
+    pub fn new(zoom: f32) -> Self {
+        Self {
+            position: (0., 0.),
+            zoom,
+            view: (0., 0.),
+            shake: 0.,
+        }
+    }
+
+    /// Multiply the zoom by `factor`, within the limits of `config`.
+    pub fn zoom_by(&mut self, factor: f32, config: &CameraConfig) {
+        self.zoom = (self.zoom * factor)
+            .max(config.min_zoom)
+            .min(config.max_zoom);
+    }
+
+    /// Shake the camera. Shakes add up until the full shake of 1.
+    pub fn shake(&mut self, amount: f32) {
+        self.shake = (self.shake + amount).min(1.);
+    }
+}
+
+impl Component for CameraController {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Create the camera of the game, looking at the origin. Its projection is set by the
+/// `CameraSystem`.
+pub fn spawn(world: &mut World) -> Entity {
+    let zoom = world.read_resource::<CameraConfig>().zoom;
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(0., 0., CAMERA_Z);
+    world
+        .create_entity()
+        .with(Camera::standard_2d(1., 1.))
+        .with(transform)
+        .with(CameraController::new(zoom))
+        .build()
+}
+
+/// Fits the projection of the `CameraController`s to the window and their zoom, follows the
+/// `CameraTarget` within the bounds, and shakes. Uses real time, so the camera stays smooth
+/// whatever the time scale.
+#[derive(Debug, Default)]
+pub struct CameraSystem;
+
+impl<'a> System<'a> for CameraSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, CameraConfig>,
+        ReadExpect<'a, ScreenDimensions>,
+        ReadStorage<'a, CameraTarget>,
+        WriteStorage<'a, CameraController>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, Transform>,
+    );
+
+    #[instrument(
+        skip(time, config, screen, targets, controllers, cameras, transforms),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            time,
+            config,
+            screen,
+            targets,
+            mut controllers,
+            mut cameras,
+            mut transforms,
+        ): Self::SystemData,
+    ) {
+        let delta = time.delta_real_seconds();
+        let target = (&targets, &transforms)
+            .join()
+            .next()
+            .map(|(_, transform)| (transform.translation().x, transform.translation().y));
+        let catch_up = if config.smoothing > 0. {
+            1. - (-config.smoothing * delta).exp()
+        } else {
+            1.
+        };
+        let mut rng = rand::thread_rng();
+
+        for (controller, camera, transform) in
+            (&mut controllers, &mut cameras, &mut transforms).join()
+        {
+            controller.zoom = controller.zoom.max(config.min_zoom).min(config.max_zoom);
+            let height = config.view_height / controller.zoom;
+            let view = (height * screen.aspect_ratio(), height);
+            if view != controller.view {
+                *camera = Camera::orthographic(
+                    -view.0 / 2.,
+                    view.0 / 2.,
+                    -view.1 / 2.,
+                    view.1 / 2.,
+                    Z_NEAR,
+                    Z_FAR,
+                );
+                controller.view = view;
+            }
+
+            if let Some(target) = target {
+                controller.position.0 += (target.0 - controller.position.0) * catch_up;
+                controller.position.1 += (target.1 - controller.position.1) * catch_up;
+            }
+            if let Some(bounds) = config.bounds {
+                controller.position = bounds.clamp(controller.position, view);
+            }
+
+            controller.shake = (controller.shake - config.shake_decay * delta).max(0.);
+            // squared so that small shakes stay subtle
+            let shake = config.max_shake * controller.shake * controller.shake;
+            transform.set_translation_x(controller.position.0 + shake * rng.gen_range(-1., 1.));
+            transform.set_translation_y(controller.position.1 + shake * rng.gen_range(-1., 1.));
+        }
+    }
+}
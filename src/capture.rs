@@ -0,0 +1,393 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use amethyst::{
+    ecs::prelude::World,
+    renderer::{
+        bundle::{
+            ImageOptions, OutputColor, RenderOrder, RenderPlan, RenderPlugin, Target, TargetImage,
+            TargetPlanOutputs,
+        },
+        pass::{DrawFlat2DDesc, DrawFlat2DTransparentDesc},
+        rendy::{
+            command::{
+                CommandBuffer, CommandPool, ExecutableState, Family, Fence, MultiShot,
+                PendingState, Queue, QueueType, SimultaneousUse, Submission, Submit, Transfer,
+            },
+            factory::Factory,
+            frame::Frames,
+            graph::{
+                gfx_acquire_barriers, gfx_release_barriers, GraphContext, ImageAccess, Node,
+                NodeBuffer, NodeBuildError, NodeDesc, NodeImage,
+            },
+            hal::{
+                self,
+                command::{BufferImageCopy, ClearDepthStencil, ClearValue},
+                format::{Aspects, Format},
+                image::{self, Kind},
+                pso::PipelineStage,
+                Backend,
+            },
+            memory::Download,
+            resource::{Buffer, BufferInfo, Escape},
+        },
+    },
+    ui::DrawUiDesc,
+    window::ScreenDimensions,
+};
+
+use tracing::{event, Level};
+
+use crate::screenshot::{Frame, FrameSource};
+
+/// Where the frames to read back are rendered.
+const CAPTURE_TARGET: Target = Target::Custom("capture");
+
+#[derive(Debug, Default)]
+struct Readback {
+    // number of requests so far, to only read the frames copied for the current one
+    requests: u64,
+    // request waiting for a frame, if any
+    requested: Option<u64>,
+    frame: Option<amethyst::Result<Frame>>,
+}
+
+/// The frames rendered to the window, read back by the `RenderCapture` plugin given a clone of
+/// it.
+#[derive(Debug, Clone, Default)]
+pub struct WindowFrames {
+    readback: Arc<Mutex<Readback>>,
+}
+
+impl WindowFrames {
+    fn lock(&self) -> MutexGuard<'_, Readback> {
+        self.readback.lock().expect("readback lock is not poisoned")
+    }
+
+    fn is_requested(&self) -> bool {
+        self.lock().requested.is_some()
+    }
+}
+
+impl FrameSource for WindowFrames {
+    fn request(&mut self) {
+        let mut readback = self.lock();
+        readback.requests += 1;
+        readback.requested = Some(readback.requests);
+        readback.frame = None;
+    }
+
+    fn take(&mut self) -> Option<amethyst::Result<Frame>> {
+        let mut readback = self.lock();
+        let frame = readback.frame.take();
+        if frame.is_some() {
+            readback.requested = None;
+        }
+        frame
+    }
+
+    fn cancel(&mut self) {
+        let mut readback = self.lock();
+        readback.requested = None;
+        readback.frame = None;
+    }
+}
+
+/// Renders the sprites and the UI a second time, into an image that is copied back to the
+/// `WindowFrames`. The window can't be read directly, it is presented as soon as it is drawn.
+///
+/// The capture pass is only in the render graph while the `WindowFrames` wait for a frame, the
+/// graph is rebuilt when a request comes in and once it is answered.
+#[derive(Debug)]
+pub struct RenderCapture {
+    frames: WindowFrames,
+    clear: [f32; 4],
+    // the capture pass is in the graph, for a window of these dimensions
+    planned: Option<ScreenDimensions>,
+}
+
+impl RenderCapture {
+    pub fn new(frames: WindowFrames) -> Self {
+        Self {
+            frames,
+            clear: [0., 0., 0., 1.],
+            planned: None,
+        }
+    }
+
+    /// Background of the captured frames, the same as the window's.
+    pub fn with_clear(mut self, clear: [f32; 4]) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    fn wanted(&self, world: &World) -> Option<ScreenDimensions> {
+        if self.frames.is_requested() {
+            world
+                .try_fetch::<ScreenDimensions>()
+                .map(|dimensions| dimensions.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderCapture {
+    fn should_rebuild(&mut self, world: &World) -> bool {
+        self.wanted(world) != self.planned
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), amethyst::Error> {
+        self.planned = self.wanted(world);
+        let dimensions = match &self.planned {
+            Some(dimensions) => dimensions,
+            None => return Ok(()),
+        };
+        event!(Level::DEBUG, "Adding the capture pass to the render graph");
+
+        let kind = Kind::D2(dimensions.width() as u32, dimensions.height() as u32, 1, 1);
+        plan.define_pass(
+            CAPTURE_TARGET,
+            TargetPlanOutputs {
+                // 8 bit RGBA rows, as the `Frame`s
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba8Srgb,
+                    clear: Some(ClearValue::Color(self.clear.into())),
+                })],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                }),
+            },
+        )?;
+        // what `RenderFlat2D` and `RenderUi` draw in the window
+        plan.extend_target(CAPTURE_TARGET, |ctx| {
+            ctx.add(RenderOrder::Opaque, DrawFlat2DDesc::new().builder())?;
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawFlat2DTransparentDesc::new().builder(),
+            )?;
+            ctx.add(RenderOrder::Overlay, DrawUiDesc::new().builder())?;
+            Ok(())
+        });
+        // nothing depends on the capture, it is pulled in by the window
+        let readback = self.frames.readback.clone();
+        plan.extend_target(Target::Main, move |ctx| {
+            let image = ctx.get_image(TargetImage::Color(CAPTURE_TARGET, 0))?;
+            let pass = ctx.get_node(CAPTURE_TARGET)?;
+            ctx.graph().add_node(
+                ReadbackDesc { readback }
+                    .builder()
+                    .with_image(image)
+                    .with_dependency(pass),
+            );
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ReadbackDesc {
+    readback: Arc<Mutex<Readback>>,
+}
+
+/// A buffer the capture image is copied into, one per frame in flight: a frame starts once
+/// the one before it in the same slot is complete, so its copy can be read then.
+#[derive(Debug)]
+struct Slot<B: Backend> {
+    buffer: Escape<Buffer<B>>,
+    submit: Submit<B, SimultaneousUse>,
+    command: CommandBuffer<B, QueueType, PendingState<ExecutableState<MultiShot<SimultaneousUse>>>>,
+    // request the last copy was made for
+    request: Option<u64>,
+}
+
+#[derive(Debug)]
+struct ReadbackNode<B: Backend> {
+    readback: Arc<Mutex<Readback>>,
+    pool: CommandPool<B, QueueType>,
+    slots: Vec<Slot<B>>,
+    width: u32,
+    height: u32,
+}
+
+impl<B: Backend> NodeDesc<B, World> for ReadbackDesc {
+    type Node = ReadbackNode<B>;
+
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: image::Access::TRANSFER_READ,
+            layout: image::Layout::TransferSrcOptimal,
+            usage: image::Usage::TRANSFER_SRC,
+            stages: PipelineStage::TRANSFER,
+        }]
+    }
+
+    fn build<'a>(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _aux: &World,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Self::Node, NodeBuildError> {
+        let node_image = images
+            .into_iter()
+            .next()
+            .expect("the capture image is given to the node");
+        let image = ctx
+            .get_image(node_image.id)
+            .expect("the graph has the capture image");
+        let extent = image.kind().extent();
+
+        let mut pool = factory
+            .create_command_pool(family)
+            .map_err(NodeBuildError::OutOfMemory)?;
+        let mut slots = vec![];
+        for initial in pool.allocate_buffers(ctx.frames_in_flight() as usize) {
+            let buffer = factory
+                .create_buffer(
+                    BufferInfo {
+                        size: u64::from(extent.width * extent.height * 4),
+                        usage: hal::buffer::Usage::TRANSFER_DST,
+                    },
+                    Download,
+                )
+                .map_err(|err| {
+                    event!(Level::ERROR, "Error creating a capture buffer: {:?}", err);
+                    NodeBuildError::OutOfMemory(hal::device::OutOfMemory::Device)
+                })?;
+
+            let mut recording = initial.begin(MultiShot(SimultaneousUse), ());
+            let mut encoder = recording.encoder();
+            let (mut stages, barriers) = gfx_acquire_barriers(ctx, None, Some(&node_image));
+            stages.start |= PipelineStage::TRANSFER;
+            stages.end |= PipelineStage::TRANSFER;
+            unsafe {
+                encoder.pipeline_barrier(stages, hal::memory::Dependencies::empty(), barriers);
+                encoder.copy_image_to_buffer(
+                    image.raw(),
+                    node_image.layout,
+                    buffer.raw(),
+                    Some(BufferImageCopy {
+                        buffer_offset: 0,
+                        buffer_width: extent.width,
+                        buffer_height: extent.height,
+                        image_layers: image::SubresourceLayers {
+                            aspects: Aspects::COLOR,
+                            level: 0,
+                            layers: 0..1,
+                        },
+                        image_offset: image::Offset::ZERO,
+                        image_extent: extent,
+                    }),
+                );
+            }
+            let (mut stages, barriers) = gfx_release_barriers(ctx, None, Some(&node_image));
+            stages.start |= PipelineStage::TRANSFER;
+            stages.end |= PipelineStage::TRANSFER;
+            unsafe {
+                encoder.pipeline_barrier(stages, hal::memory::Dependencies::empty(), barriers);
+            }
+
+            let (submit, command) = recording.finish().submit();
+            slots.push(Slot {
+                buffer,
+                submit,
+                command,
+                request: None,
+            });
+        }
+
+        Ok(ReadbackNode {
+            readback: self.readback,
+            pool,
+            slots,
+            width: extent.width,
+            height: extent.height,
+        })
+    }
+}
+
+/// The pixels copied in `buffer`, whose copy must be complete.
+unsafe fn read_frame<B: Backend>(
+    factory: &Factory<B>,
+    buffer: &mut Buffer<B>,
+    width: u32,
+    height: u32,
+) -> amethyst::Result<Frame> {
+    let size = buffer.size();
+    let mut mapped = buffer
+        .map(factory.device(), 0..size)
+        .map_err(|err| amethyst::Error::from_string(format!("{:?}", err)))?;
+    let pixels = mapped
+        .read::<u8>(factory.device(), 0..size)
+        .map_err(|err| amethyst::Error::from_string(format!("{:?}", err)))?
+        .to_vec();
+    Ok(Frame {
+        width,
+        height,
+        pixels,
+    })
+}
+
+impl<B: Backend> Node<B, World> for ReadbackNode<B> {
+    type Capability = Transfer;
+
+    fn run<'a>(
+        &mut self,
+        _ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        queue: &mut Queue<B>,
+        _aux: &World,
+        frames: &Frames<B>,
+        waits: &[(&'a B::Semaphore, PipelineStage)],
+        signals: &[&'a B::Semaphore],
+        fence: Option<&mut Fence<B>>,
+    ) {
+        let index = (frames.next().index() % self.slots.len() as u64) as usize;
+        let slot = &mut self.slots[index];
+        let mut readback = self.readback.lock().expect("readback lock is not poisoned");
+        if readback.frame.is_none()
+            && readback.requested.is_some()
+            && slot.request == readback.requested
+        {
+            let frame = unsafe { read_frame(factory, &mut slot.buffer, self.width, self.height) };
+            readback.frame = Some(frame);
+        }
+        slot.request = readback.requested;
+        drop(readback);
+
+        unsafe {
+            queue.submit(
+                Some(
+                    Submission::new()
+                        .submits(Some(&slot.submit))
+                        .wait(waits.iter().cloned())
+                        .signal(signals.iter().cloned()),
+                ),
+                fence,
+            );
+        }
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _aux: &World) {
+        self.pool.free_buffers(
+            self.slots
+                .drain(..)
+                .map(|slot| slot.command.mark_complete()),
+        );
+        factory.destroy_command_pool(self.pool);
+    }
+}
@@ -0,0 +1,1012 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    io::{self, Write as _},
+    sync::{Arc, Mutex},
+};
+
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    core::transform::Parent,
+    derive::SystemDesc,
+    ecs::prelude::{
+        Entities, Entity, LazyUpdate, Read, ReadExpect, System, SystemData, Write, WriteStorage,
+    },
+    input::{InputEvent, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, FontAsset, Stretch, TtfFormat, UiImage, UiText, UiTransform},
+    winit::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+};
+
+use tracing::instrument;
+use tracing_subscriber::{fmt::MakeWriter, reload, EnvFilter};
+
+use crate::{
+    bindings,
+    loading::Preloaded,
+    ui_scheme::{COLOR_3, COLOR_TEXT_LIGHT},
+};
+
+const FONT: &str = "font/mandrill.ttf";
+const SCROLLBACK_LENGTH: usize = 500;
+const HISTORY_LENGTH: usize = 100;
+const VISIBLE_LINES: usize = 14;
+const LINE_HEIGHT: f32 = 24.;
+const MARGIN: f32 = 10.;
+const BACKGROUND: [f32; 4] = [0., 0., 0., 0.85];
+
+fn error(message: String) -> amethyst::Error {
+    amethyst::Error::from_string(message)
+}
+
+/// Kind of value a command parameter takes.
+#[derive(Debug, Clone)]
+pub enum ArgKind {
+    Integer,
+    Number,
+    /// One of these words, offered by the autocompletion.
+    Choice(Vec<String>),
+    /// The rest of the line.
+    Text,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+impl Param {
+    pub fn new(name: &'static str, kind: ArgKind) -> Self {
+        Self {
+            name,
+            kind,
+            optional: false,
+        }
+    }
+
+    /// A parameter that can be left out. Only the last ones of a command can be optional.
+    pub fn optional(name: &'static str, kind: ArgKind) -> Self {
+        Self {
+            optional: true,
+            ..Self::new(name, kind)
+        }
+    }
+
+    fn describe(&self) -> String {
+        if self.optional {
+            format!("[{}]", self.name)
+        } else {
+            format!("<{}>", self.name)
+        }
+    }
+
+    fn parse(&self, word: &str) -> amethyst::Result<Arg> {
+        match &self.kind {
+            ArgKind::Integer => word
+                .parse()
+                .map(Arg::Integer)
+                .map_err(|_| error(format!("{} should be an integer, not {}", self.name, word))),
+            ArgKind::Number => word
+                .parse()
+                .map(Arg::Number)
+                .map_err(|_| error(format!("{} should be a number, not {}", self.name, word))),
+            ArgKind::Choice(choices) if choices.iter().any(|choice| choice == word) => {
+                Ok(Arg::Text(word.to_string()))
+            }
+            ArgKind::Choice(choices) => Err(error(format!(
+                "{} should be one of {}, not {}",
+                self.name,
+                choices.join(", "),
+                word
+            ))),
+            ArgKind::Text => Ok(Arg::Text(word.to_string())),
+        }
+    }
+}
+
+/// A parsed argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Integer(i64),
+    Number(f32),
+    Text(String),
+}
+
+/// Arguments of a command, in the order of its parameters. Optional ones may be missing.
+#[derive(Debug, Clone, Default)]
+pub struct Args(Vec<Arg>);
+
+impl Args {
+    pub fn integer(&self, index: usize) -> Option<i64> {
+        match self.0.get(index) {
+            Some(Arg::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, index: usize) -> Option<f32> {
+        match self.0.get(index) {
+            Some(Arg::Number(value)) => Some(*value),
+            Some(Arg::Integer(value)) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, index: usize) -> Option<&str> {
+        match self.0.get(index) {
+            Some(Arg::Text(value)) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Runs a command, returning what to print in the console.
+pub type Handler = Arc<dyn Fn(&mut World, &Args) -> amethyst::Result<String> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Command {
+    /// Can have several words, like `state push`.
+    pub name: String,
+    pub help: String,
+    pub params: Vec<Param>,
+    handler: Handler,
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .finish()
+    }
+}
+
+impl Command {
+    /// How to call the command, like `spawn <what> [count]`.
+    pub fn usage(&self) -> String {
+        std::iter::once(self.name.clone())
+            .chain(self.params.iter().map(Param::describe))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn word_count(&self) -> usize {
+        self.name.split(' ').count()
+    }
+
+    fn parse(&self, words: &[&str]) -> amethyst::Result<Args> {
+        let mut args = Vec::new();
+        for (i, param) in self.params.iter().enumerate() {
+            if words.len() <= i {
+                if param.optional {
+                    break;
+                }
+                return Err(error(format!(
+                    "missing {}, usage: {}",
+                    param.describe(),
+                    self.usage()
+                )));
+            }
+            if let ArgKind::Text = param.kind {
+                args.push(param.parse(&words[i..].join(" "))?);
+                return Ok(Args(args));
+            }
+            args.push(param.parse(words[i])?);
+        }
+        if words.len() > self.params.len() {
+            return Err(error(format!(
+                "too many arguments, usage: {}",
+                self.usage()
+            )));
+        }
+        Ok(Args(args))
+    }
+}
+
+/// Commands available in the console, by name. Anything with access to the `World` can register
+/// some: states usually do it when they start and remove them when they stop.
+#[derive(Debug, Default)]
+pub struct Commands {
+    commands: BTreeMap<String, Command>,
+}
+
+impl Commands {
+    /// The commands of the console itself.
+    pub fn builtin() -> Self {
+        let mut commands = Self::default();
+        commands.register("help", "List the commands", vec![], |world, _| {
+            Ok(world
+                .read_resource::<Commands>()
+                .commands
+                .values()
+                .map(|command| format!("{} - {}", command.usage(), command.help))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        });
+        commands.register("clear", "Clear the console", vec![], |world, _| {
+            world.write_resource::<Console>().clear();
+            Ok(String::new())
+        });
+        commands.register(
+            "log level",
+            "Change which events are logged, like info,corylus=trace",
+            vec![Param::new("filter", ArgKind::Text)],
+            |world, args| {
+                let directives = args.text(0).unwrap_or_default();
+                world
+                    .try_fetch::<LogFilter>()
+                    .ok_or_else(|| error("the log filter can't be changed".to_string()))?
+                    .set(directives)?;
+                Ok(format!("Log filter: {}", directives))
+            },
+        );
+        commands
+    }
+
+    /// Add a command, replacing the one with the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        help: &str,
+        params: Vec<Param>,
+        handler: impl Fn(&mut World, &Args) -> amethyst::Result<String> + Send + Sync + 'static,
+    ) {
+        self.commands.insert(
+            name.to_string(),
+            Command {
+                name: name.to_string(),
+                help: help.to_string(),
+                params,
+                handler: Arc::new(handler),
+            },
+        );
+    }
+
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    /// The command with the longest name at the start of `words`, with the words left for its
+    /// arguments.
+    fn find<'w>(&self, words: &'w [&'w str]) -> Option<(&Command, &'w [&'w str])> {
+        self.commands
+            .values()
+            .filter(|command| words.starts_with(&command.name.split(' ').collect::<Vec<_>>()))
+            .max_by_key(|command| command.word_count())
+            .map(|command| (command, &words[command.word_count()..]))
+    }
+
+    /// The command to run for `line`, with its arguments.
+    pub fn parse(&self, line: &str) -> amethyst::Result<(Handler, Args)> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (command, words) = self
+            .find(&words)
+            .ok_or_else(|| error(format!("unknown command: {}", line)))?;
+        Ok((command.handler.clone(), command.parse(words)?))
+    }
+
+    /// Complete the last word of `line`, as far as all the candidates agree. The candidates are
+    /// returned when there are several.
+    pub fn complete(&self, line: &str) -> (String, Vec<String>) {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        // the word being typed, nothing yet after a space
+        let (typed, partial) = match words.split_last() {
+            Some((last, typed)) if !line.ends_with(' ') => (typed, *last),
+            _ => (&words[..], ""),
+        };
+
+        // next word of the command names, or choices of the next parameter
+        let mut candidates = self
+            .commands
+            .keys()
+            .filter_map(|name| {
+                let name = name.split(' ').collect::<Vec<_>>();
+                if name.len() > typed.len() && name.starts_with(typed) {
+                    Some(name[typed.len()].to_string())
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some((command, args)) = self.find(typed) {
+            if let Some(ArgKind::Choice(choices)) =
+                command.params.get(args.len()).map(|param| &param.kind)
+            {
+                candidates.extend(choices.iter().cloned());
+            }
+        }
+        candidates.retain(|candidate| candidate.starts_with(partial));
+        candidates.sort();
+        candidates.dedup();
+
+        let start = &line[..line.len() - partial.len()];
+        match candidates.len() {
+            0 => (line.to_string(), vec![]),
+            1 => (format!("{}{} ", start, candidates[0]), vec![]),
+            _ => (
+                format!("{}{}", start, common_prefix(&candidates)),
+                candidates,
+            ),
+        }
+    }
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let first = &words[0];
+    let length = words
+        .iter()
+        .map(|word| {
+            first
+                .chars()
+                .zip(word.chars())
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+        .min()
+        .unwrap_or(0);
+    first.chars().take(length).collect()
+}
+
+/// The developer console: what is being typed, what was typed before, and what was printed.
+#[derive(Debug, Default)]
+pub struct Console {
+    pub open: bool,
+    // closed during the last frame, states still get the events of that frame
+    just_closed: bool,
+    input: String,
+    history: Vec<String>,
+    // position when browsing the history, from the most recent
+    history_index: Option<usize>,
+    scrollback: VecDeque<String>,
+    // the displayed texts are out of date
+    changed: bool,
+}
+
+impl Console {
+    /// Whether the typed keys are for the console. Actions should be ignored then, the keys
+    /// bound to them are typed in the console.
+    pub fn captures_input(&self) -> bool {
+        self.open || self.just_closed
+    }
+
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.scrollback.len() == SCROLLBACK_LENGTH {
+                self.scrollback.pop_front();
+            }
+            self.scrollback.push_back(line.to_string());
+        }
+        self.changed = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.scrollback.clear();
+        self.changed = true;
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+        self.changed = true;
+    }
+
+    fn type_character(&mut self, character: char) {
+        match character {
+            // backspace
+            '\u{8}' => {
+                self.input.pop();
+            }
+            c if !c.is_control() => self.input.push(c),
+            _ => return,
+        }
+        self.changed = true;
+    }
+
+    /// Take the typed line to run it, keeping it in the history.
+    fn submit(&mut self) -> Option<String> {
+        let line = self.input.trim().to_string();
+        self.set_input(String::new());
+        self.history_index = None;
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > HISTORY_LENGTH {
+                self.history.remove(0);
+            }
+        }
+        self.print(&format!("> {}", line));
+        Some(line)
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        self.history_index = match (self.history_index, older) {
+            (None, true) => Some(0),
+            (None, false) => None,
+            (Some(index), true) => Some((index + 1).min(self.history.len() - 1)),
+            (Some(0), false) => None,
+            (Some(index), false) => Some(index - 1),
+        };
+        let input = match self.history_index {
+            Some(index) => self.history[self.history.len() - 1 - index].clone(),
+            None => String::new(),
+        };
+        self.set_input(input);
+    }
+}
+
+/// Whether `event` is typing meant for the console, which states should ignore: the inputs and
+/// typed characters while it captures them.
+pub fn is_typing(world: &World, event: &StateEvent) -> bool {
+    let typing = match event {
+        StateEvent::Input(_) => true,
+        StateEvent::Window(Event::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(_),
+            ..
+        }) => true,
+        _ => false,
+    };
+    typing
+        && world
+            .try_fetch::<Console>()
+            .map_or(false, |console| console.captures_input())
+}
+
+/// `text` without the escape sequences used for colors.
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // sequences end with a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Lines logged by `tracing`, waiting to be printed in the console. Clones share the same lines.
+///
+/// It is given to the `tracing` subscriber as its writer, and still writes everything to the
+/// standard output.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogBuffer {
+    fn take(&self) -> Vec<String> {
+        match self.lines.lock() {
+            Ok(mut lines) => lines.drain(..).collect(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+impl MakeWriter for LogBuffer {
+    type Writer = LogWriter;
+
+    fn make_writer(&self) -> Self::Writer {
+        LogWriter(self.clone())
+    }
+}
+
+pub struct LogWriter(LogBuffer);
+
+impl io::Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stdout().write_all(buf)?;
+        let text = strip_ansi(&String::from_utf8_lossy(buf));
+        if let Ok(mut lines) = (self.0).lines.lock() {
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                if lines.len() == SCROLLBACK_LENGTH {
+                    lines.pop_front();
+                }
+                lines.push_back(line.to_string());
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Changes which `tracing` events are logged while the game runs.
+pub struct LogFilter {
+    reload: Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>,
+}
+
+impl LogFilter {
+    pub fn new(
+        reload: impl Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            reload: Box::new(reload),
+        }
+    }
+
+    /// Use `directives`, written like `RUST_LOG`.
+    pub fn set(&self, directives: &str) -> amethyst::Result<()> {
+        (self.reload)(EnvFilter::try_new(directives)?)?;
+        Ok(())
+    }
+}
+
+/// Entities of the open console.
+#[derive(Debug)]
+struct ConsoleUi {
+    root: Entity,
+    lines: Vec<Entity>,
+    input: Entity,
+}
+
+/// Opens and closes the `Console` with its action, in any state, and handles what is typed
+/// in it. Commands are run at the end of the frame, with access to the whole `World`.
+///
+/// While the console is open, `Console::captures_input` tells the other systems and the states
+/// to ignore the actions, so that typing doesn't trigger them.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(ConsoleSystemDesc))]
+pub struct ConsoleSystem {
+    #[system_desc(event_channel_reader)]
+    input_reader_id: ReaderId<InputEvent<StringBindings>>,
+    #[system_desc(event_channel_reader)]
+    window_reader_id: ReaderId<Event>,
+    #[system_desc(skip)]
+    font: Option<Handle<FontAsset>>,
+    #[system_desc(skip)]
+    ui: Option<ConsoleUi>,
+}
+
+impl ConsoleSystem {
+    pub fn new(
+        input_reader_id: ReaderId<InputEvent<StringBindings>>,
+        window_reader_id: ReaderId<Event>,
+    ) -> Self {
+        Self {
+            input_reader_id,
+            window_reader_id,
+            font: None,
+            ui: None,
+        }
+    }
+
+    fn run_command(commands: &Commands, lazy: &LazyUpdate, console: &mut Console, line: &str) {
+        match commands.parse(line) {
+            Ok((handler, args)) => lazy.exec_mut(move |world| {
+                let output = handler(world, &args).unwrap_or_else(|err| format!("Error: {}", err));
+                world.write_resource::<Console>().print(&output);
+            }),
+            Err(err) => console.print(&format!("Error: {}", err)),
+        }
+    }
+
+    fn create_ui(
+        &self,
+        entities: &Entities,
+        font: Handle<FontAsset>,
+        transforms: &mut WriteStorage<UiTransform>,
+        texts: &mut WriteStorage<UiText>,
+        images: &mut WriteStorage<UiImage>,
+        parents: &mut WriteStorage<Parent>,
+    ) -> ConsoleUi {
+        let root = entities
+            .build_entity()
+            .with(
+                UiTransform::new(
+                    "console".to_string(),
+                    Anchor::TopMiddle,
+                    Anchor::TopMiddle,
+                    0.,
+                    0.,
+                    200.,
+                    20.,
+                    (VISIBLE_LINES + 1) as f32 * LINE_HEIGHT + 3. * MARGIN,
+                )
+                .with_stretch(Stretch::X { x_margin: 0. }),
+                transforms,
+            )
+            .with(UiImage::SolidColor(BACKGROUND), images)
+            .build();
+
+        let mut create_line = |id: String, anchor: Anchor, y: f32, color: [f32; 4]| {
+            let mut text = UiText::new(font.clone(), String::new(), color, 20.);
+            text.align = Anchor::MiddleLeft;
+            entities
+                .build_entity()
+                .with(
+                    UiTransform::new(id, anchor, anchor, 0., y, 1., 20., LINE_HEIGHT)
+                        .with_stretch(Stretch::X { x_margin: MARGIN }),
+                    transforms,
+                )
+                .with(text, texts)
+                .with(Parent { entity: root }, parents)
+                .build()
+        };
+        let lines = (0..VISIBLE_LINES)
+            .map(|i| {
+                create_line(
+                    format!("console_line_{}", i),
+                    Anchor::TopMiddle,
+                    -MARGIN - i as f32 * LINE_HEIGHT,
+                    COLOR_TEXT_LIGHT,
+                )
+            })
+            .collect();
+        let input = create_line(
+            "console_input".to_string(),
+            Anchor::BottomMiddle,
+            MARGIN,
+            COLOR_3,
+        );
+
+        ConsoleUi { root, lines, input }
+    }
+}
+
+impl<'a> System<'a> for ConsoleSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Console>,
+        Read<'a, Commands>,
+        Read<'a, LogBuffer>,
+        Read<'a, LazyUpdate>,
+        Read<'a, EventChannel<InputEvent<StringBindings>>>,
+        Read<'a, EventChannel<Event>>,
+        Option<Read<'a, Preloaded>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<FontAsset>>,
+        WriteStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+        WriteStorage<'a, UiImage>,
+        WriteStorage<'a, Parent>,
+    );
+
+    #[instrument(
+        skip(
+            entities,
+            console,
+            commands,
+            log,
+            lazy,
+            input_events,
+            window_events,
+            preloaded,
+            loader,
+            fonts,
+            transforms,
+            texts,
+            images,
+            parents
+        ),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut console,
+            commands,
+            log,
+            lazy,
+            input_events,
+            window_events,
+            preloaded,
+            loader,
+            fonts,
+            mut transforms,
+            mut texts,
+            mut images,
+            mut parents,
+        ): Self::SystemData,
+    ) {
+        let was_open = console.open;
+        let mut toggled = false;
+        for input_event in input_events.read(&mut self.input_reader_id) {
+            if let InputEvent::ActionPressed(action) = input_event {
+                if action == bindings::CONSOLE {
+                    console.open = !console.open;
+                    toggled = true;
+                }
+            }
+        }
+
+        let logged = log.take();
+        if !logged.is_empty() {
+            console.print(&logged.join("\n"));
+        }
+
+        for window_event in window_events.read(&mut self.window_reader_id) {
+            // the key opening or closing the console is not typed in it
+            if !console.open || toggled {
+                continue;
+            }
+            let window_event = match window_event {
+                Event::WindowEvent { event, .. } => event,
+                _ => continue,
+            };
+            match window_event {
+                WindowEvent::ReceivedCharacter(character) => console.type_character(*character),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => match key {
+                    VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                        if let Some(line) = console.submit() {
+                            Self::run_command(&commands, &lazy, &mut console, &line);
+                        }
+                    }
+                    VirtualKeyCode::Up => console.browse_history(true),
+                    VirtualKeyCode::Down => console.browse_history(false),
+                    VirtualKeyCode::Tab => {
+                        let (line, candidates) = commands.complete(&console.input);
+                        if !candidates.is_empty() {
+                            console.print(&candidates.join("  "));
+                        }
+                        console.set_input(line);
+                    }
+                    VirtualKeyCode::Escape => console.open = false,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        console.just_closed = was_open && !console.open;
+
+        if !console.open {
+            if let Some(ui) = self.ui.take() {
+                // the lines are deleted with their parent
+                let _ = entities.delete(ui.root);
+            }
+            return;
+        }
+
+        if self.ui.is_none() {
+            let font = self
+                .font
+                .get_or_insert_with(|| {
+                    preloaded
+                        .and_then(|preloaded| preloaded.fonts.get(FONT).cloned())
+                        .unwrap_or_else(|| loader.load(FONT, TtfFormat, (), &fonts))
+                })
+                .clone();
+            self.ui = Some(self.create_ui(
+                &entities,
+                font,
+                &mut transforms,
+                &mut texts,
+                &mut images,
+                &mut parents,
+            ));
+            console.changed = true;
+        }
+
+        if console.changed {
+            let ui = self.ui.as_ref().expect("console UI is created above");
+            // the most recent lines, at the bottom
+            let skipped = console.scrollback.len().saturating_sub(VISIBLE_LINES);
+            let mut displayed = console.scrollback.iter().skip(skipped);
+            for entity in &ui.lines {
+                if let Some(text) = texts.get_mut(*entity) {
+                    text.text = displayed.next().cloned().unwrap_or_default();
+                }
+            }
+            if let Some(text) = texts.get_mut(ui.input) {
+                text.text = format!("> {}_", console.input);
+            }
+            console.changed = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> Commands {
+        let mut commands = Commands::default();
+        commands.register(
+            "spawn",
+            "Spawn things",
+            vec![
+                Param::new(
+                    "what",
+                    ArgKind::Choice(vec!["star".to_string(), "rock".to_string()]),
+                ),
+                Param::optional("count", ArgKind::Integer),
+            ],
+            |_, _| Ok("spawn".to_string()),
+        );
+        commands.register("state", "Show the states", vec![], |_, _| {
+            Ok("state".to_string())
+        });
+        commands.register("state pop", "Pop a state", vec![], |_, _| {
+            Ok("state pop".to_string())
+        });
+        commands.register(
+            "state push",
+            "Push a state",
+            vec![Param::new("name", ArgKind::Text)],
+            |_, _| Ok("state push".to_string()),
+        );
+        commands
+    }
+
+    fn run(commands: &Commands, line: &str) -> amethyst::Result<(String, Args)> {
+        let (handler, args) = commands.parse(line)?;
+        Ok((handler(&mut World::new(), &args)?, args))
+    }
+
+    #[test]
+    fn parses_arguments_by_kind() {
+        let commands = commands();
+        let (name, args) = run(&commands, "spawn star 3").unwrap();
+        assert_eq!(name, "spawn");
+        assert_eq!(args.text(0), Some("star"));
+        assert_eq!(args.integer(1), Some(3));
+        assert_eq!(args.number(1), Some(3.));
+
+        let (_, args) = run(&commands, "  spawn   rock ").unwrap();
+        assert_eq!(args.text(0), Some("rock"));
+        assert_eq!(args.integer(1), None);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let commands = commands();
+        assert!(commands.parse("spawn").is_err());
+        assert!(commands.parse("spawn comet").is_err());
+        assert!(commands.parse("spawn star many").is_err());
+        assert!(commands.parse("spawn star 3 4").is_err());
+        assert!(commands.parse("despawn star").is_err());
+        assert!(commands.parse("").is_err());
+    }
+
+    #[test]
+    fn longest_command_name_wins() {
+        let commands = commands();
+        assert_eq!(run(&commands, "state").unwrap().0, "state");
+        assert_eq!(run(&commands, "state pop").unwrap().0, "state pop");
+        // text takes the rest of the line
+        let (name, args) = run(&commands, "state push main  menu").unwrap();
+        assert_eq!(name, "state push");
+        assert_eq!(args.text(0), Some("main menu"));
+    }
+
+    #[test]
+    fn completes_command_names() {
+        let commands = commands();
+        assert_eq!(commands.complete("sp"), ("spawn ".to_string(), vec![]));
+        assert_eq!(
+            commands.complete("state pu"),
+            ("state push ".to_string(), vec![])
+        );
+        assert_eq!(
+            commands.complete("state "),
+            (
+                "state p".to_string(),
+                vec!["pop".to_string(), "push".to_string()]
+            )
+        );
+        assert_eq!(
+            commands.complete(""),
+            (
+                "s".to_string(),
+                vec!["spawn".to_string(), "state".to_string()]
+            )
+        );
+        assert_eq!(commands.complete("xyz"), ("xyz".to_string(), vec![]));
+    }
+
+    #[test]
+    fn completes_choices() {
+        let commands = commands();
+        assert_eq!(
+            commands.complete("spawn s"),
+            ("spawn star ".to_string(), vec![])
+        );
+        assert_eq!(
+            commands.complete("spawn "),
+            (
+                "spawn ".to_string(),
+                vec!["rock".to_string(), "star".to_string()]
+            )
+        );
+        // the count has no choices
+        assert_eq!(
+            commands.complete("spawn star "),
+            ("spawn star ".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn common_prefix_is_in_characters() {
+        let words = |words: &[&str]| {
+            words
+                .iter()
+                .map(|word| word.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(common_prefix(&words(&["push", "pop"])), "p");
+        assert_eq!(common_prefix(&words(&["abc"])), "abc");
+        assert_eq!(common_prefix(&words(&["abc", "abd", "ab"])), "ab");
+        assert_eq!(common_prefix(&words(&["star", "rock"])), "");
+        assert_eq!(common_prefix(&words(&["été", "étoile"])), "ét");
+    }
+
+    fn submit(console: &mut Console, line: &str) {
+        console.set_input(line.to_string());
+        console.submit();
+    }
+
+    #[test]
+    fn browses_history_from_the_most_recent() {
+        let mut console = Console::default();
+        console.browse_history(true);
+        assert_eq!(console.input, "");
+
+        for line in &["one", "two", "two", "three"] {
+            submit(&mut console, line);
+        }
+        assert_eq!(console.history, vec!["one", "two", "three"]);
+
+        let mut browse = |older| {
+            console.browse_history(older);
+            console.input.clone()
+        };
+        assert_eq!(browse(true), "three");
+        assert_eq!(browse(true), "two");
+        assert_eq!(browse(true), "one");
+        // stops at the oldest
+        assert_eq!(browse(true), "one");
+        assert_eq!(browse(false), "two");
+        assert_eq!(browse(false), "three");
+        // back to an empty line
+        assert_eq!(browse(false), "");
+        assert_eq!(browse(false), "");
+    }
+
+    #[test]
+    fn submitting_resets_history_browsing() {
+        let mut console = Console::default();
+        submit(&mut console, "one");
+        submit(&mut console, "two");
+        console.browse_history(true);
+        console.browse_history(true);
+        submit(&mut console, "  ");
+        assert_eq!(console.history, vec!["one", "two"]);
+        console.browse_history(true);
+        assert_eq!(console.input, "two");
+    }
+
+    #[test]
+    fn strips_color_sequences() {
+        assert_eq!(strip_ansi("plain text"), "plain text");
+        assert_eq!(
+            strip_ansi("\u{1b}[31mred\u{1b}[0m and \u{1b}[1;32mbold green\u{1b}[0m"),
+            "red and bold green"
+        );
+        assert_eq!(strip_ansi("\u{1b}[2m2020-01-01\u{1b}[0m é"), "2020-01-01 é");
+    }
+}
@@ -0,0 +1,207 @@
+use amethyst::{
+    input::{is_close_requested, Button, InputEvent, InputHandler, StringBindings},
+    prelude::*,
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console,
+    focus::UiFocus,
+    router,
+    ui_scheme::{Menu, MenuButton, MenuScreen},
+};
+
+/// Lists the named actions with their bindings. Activating one waits for a key or controller
+/// button press to bind it, bindings are saved in the player config after each change.
+#[derive(Default, Debug)]
+pub struct ControlsScreen {
+    screen: MenuScreen,
+    // action waiting for a button to be bound
+    waiting: Option<String>,
+    // inputs of the current frame were already used, and should not be handled again
+    ignore_inputs: bool,
+}
+
+impl ControlsScreen {
+    /// Rebuild the screen to display the current bindings, then focus `focused`.
+    fn refresh(&mut self, world: &mut World, notice: &str, focused: UiAction) {
+        let mut buttons = {
+            let input = world.read_resource::<InputHandler<StringBindings>>();
+            bindings::REBINDABLE
+                .iter()
+                .map(|(action, label)| {
+                    let binding = if self.waiting.as_deref() == Some(*action) {
+                        format!(
+                            "press a key or button ({} to cancel)",
+                            bindings::describe(&input.bindings, bindings::BACK)
+                        )
+                    } else {
+                        bindings::describe(&input.bindings, action)
+                    };
+                    MenuButton::new(
+                        &format!("{}: {}", label, binding),
+                        &format!("bind_{}", action),
+                        UiAction::Rebind(action.to_string()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        buttons.push(MenuButton::new(
+            "Reset to defaults",
+            "bindings_reset",
+            UiAction::ResetBindings,
+        ));
+        buttons.push(MenuButton::new("Back", "controls_back", UiAction::Back));
+
+        let menu = Menu {
+            button_size: (900., 55.),
+            ..Menu::new("controls", buttons)
+        };
+        self.screen.show(world, menu, notice, &focused);
+    }
+
+    fn bind(&mut self, world: &mut World, action: String, button: Button) {
+        let notice = {
+            let mut input = world.write_resource::<InputHandler<StringBindings>>();
+            let name = bindings::describe_button(&button);
+            match bindings::rebind(&mut input.bindings, &action, button) {
+                Ok(()) => match bindings::save(&input.bindings) {
+                    Ok(()) => format!("{} bound to {}", name, action),
+                    Err(err) => {
+                        event!(Level::WARN, "Error saving bindings: {}", err);
+                        format!("{} bound to {}, but could not be saved", name, action)
+                    }
+                },
+                Err(other) => format!("{} is already used by {}", name, other),
+            }
+        };
+        self.stop_waiting(world);
+        self.refresh(world, &notice, UiAction::Rebind(action));
+    }
+
+    fn stop_waiting(&mut self, world: &mut World) {
+        self.waiting = None;
+        self.ignore_inputs = true;
+        world.write_resource::<UiFocus>().suspended = false;
+    }
+
+    fn reset(&mut self, world: &mut World) {
+        let notice = match bindings::load_defaults() {
+            Ok(defaults) => {
+                let mut input = world.write_resource::<InputHandler<StringBindings>>();
+                input.bindings = defaults;
+                match bindings::save(&input.bindings) {
+                    Ok(()) => "Bindings reset to defaults".to_string(),
+                    Err(err) => {
+                        event!(Level::WARN, "Error saving bindings: {}", err);
+                        "Bindings reset, but could not be saved".to_string()
+                    }
+                }
+            }
+            Err(err) => {
+                event!(Level::WARN, "Error loading default bindings: {}", err);
+                "Could not load default bindings".to_string()
+            }
+        };
+        self.refresh(world, &notice, UiAction::ResetBindings);
+    }
+}
+
+impl SimpleState for ControlsScreen {
+    #[instrument(skip(data), level = "info")]
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.refresh(
+            data.world,
+            "Select an action to change its binding",
+            UiAction::Rebind(bindings::REBINDABLE[0].0.to_string()),
+        );
+    }
+
+    #[instrument(skip(_data), level = "info")]
+    fn update(&mut self, _data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        self.ignore_inputs = false;
+        Trans::None
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        let world = data.world;
+
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                event!(Level::INFO, "Quitting Application!");
+                return Trans::Quit;
+            }
+        }
+        if console::is_typing(world, &event) {
+            return Trans::None;
+        }
+        if self.ignore_inputs {
+            if let StateEvent::Input(_) = &event {
+                return Trans::None;
+            }
+        }
+
+        if let Some(action) = self.waiting.clone() {
+            let pressed = match &event {
+                StateEvent::Input(InputEvent::KeyPressed { key_code, .. }) => {
+                    Some(Button::Key(*key_code))
+                }
+                StateEvent::Input(InputEvent::ControllerButtonPressed { which, button }) => {
+                    Some(Button::Controller(*which, *button))
+                }
+                _ => None,
+            };
+            if let Some(pressed) = pressed {
+                let cancel = bindings::is_bound(
+                    &world
+                        .read_resource::<InputHandler<StringBindings>>()
+                        .bindings,
+                    bindings::BACK,
+                    &pressed,
+                );
+                if cancel {
+                    self.stop_waiting(world);
+                    self.refresh(world, "Cancelled", UiAction::Rebind(action));
+                } else {
+                    self.bind(world, action, pressed);
+                }
+            }
+            return Trans::None;
+        }
+
+        if bindings::is_action(&event, bindings::BACK) {
+            return router::go(world, router::BACK);
+        }
+
+        match ui_action(world, &event) {
+            Some(UiAction::Rebind(action)) => {
+                self.waiting = Some(action.clone());
+                // the key that activated the button should not be bound
+                self.ignore_inputs = true;
+                world.write_resource::<UiFocus>().suspended = true;
+                self.refresh(world, "", UiAction::Rebind(action));
+                Trans::None
+            }
+            Some(UiAction::ResetBindings) => {
+                self.reset(world);
+                Trans::None
+            }
+            Some(UiAction::Back) => router::go(world, router::BACK),
+            _ => Trans::None,
+        }
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        self.screen.delete(data.world);
+        self.waiting = None;
+        data.world.write_resource::<UiFocus>().suspended = false;
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::VecDeque;
+
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    core::{transform::Parent, Time, Transform},
+    derive::SystemDesc,
+    ecs::prelude::{
+        Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, Write,
+        WriteStorage,
+    },
+    input::{InputEvent, StringBindings},
+    prelude::*,
+    renderer::SpriteRender,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, FontAsset, TtfFormat, UiImage, UiText, UiTransform},
+    utils::fps_counter::FpsCounter,
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::{
+    bindings, loading::Preloaded, pickup::Pickup, router::Router, starfield::Star,
+    ui_scheme::COLOR_TEXT_LIGHT,
+};
+
+const FONT: &str = "font/mandrill.ttf";
+const MARGIN: f32 = 10.;
+const WIDTH: f32 = 560.;
+const LINE_HEIGHT: f32 = 26.;
+const LINE_COUNT: usize = 6;
+const GRAPH_SAMPLES: usize = 80;
+const GRAPH_HEIGHT: f32 = 80.;
+// frame time at the top of the graph, longer frames are cut
+const GRAPH_MAX_SECONDS: f32 = 1. / 20.;
+// the texts are only refreshed a few times per second, to stay readable
+const TEXT_REFRESH: f32 = 0.25;
+const BACKGROUND: [f32; 4] = [0., 0., 0., 0.7];
+const FAST_FRAME: [f32; 4] = [0.2, 0.8, 0.2, 1.];
+const SLOW_FRAME: [f32; 4] = [0.9, 0.7, 0.1, 1.];
+const DROPPED_FRAME: [f32; 4] = [0.9, 0.2, 0.2, 1.];
+
+/// Whether the debug overlay is displayed, toggled with the debug overlay action in any state.
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+}
+
+/// Entities of the displayed overlay.
+#[derive(Debug)]
+struct OverlayUi {
+    root: Entity,
+    lines: Vec<Entity>,
+    bars: Vec<Entity>,
+}
+
+/// Displays the `DebugOverlay` at the top left of the window: the frame rate, a graph of the
+/// last frame times, entity and component counts, the screens of the state stack and the
+/// preloaded assets.
+///
+/// Nothing is created or measured while the overlay is hidden.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(DebugOverlaySystemDesc))]
+pub struct DebugOverlaySystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<InputEvent<StringBindings>>,
+    #[system_desc(skip)]
+    font: Option<Handle<FontAsset>>,
+    #[system_desc(skip)]
+    ui: Option<OverlayUi>,
+    // last frame times in seconds, oldest first
+    #[system_desc(skip)]
+    frame_times: VecDeque<f32>,
+    #[system_desc(skip)]
+    since_refresh: f32,
+}
+
+impl DebugOverlaySystem {
+    pub fn new(reader_id: ReaderId<InputEvent<StringBindings>>) -> Self {
+        Self {
+            reader_id,
+            font: None,
+            ui: None,
+            frame_times: VecDeque::with_capacity(GRAPH_SAMPLES),
+            since_refresh: 0.,
+        }
+    }
+
+    fn create_ui(
+        &self,
+        entities: &Entities,
+        font: Handle<FontAsset>,
+        transforms: &mut WriteStorage<UiTransform>,
+        texts: &mut WriteStorage<UiText>,
+        images: &mut WriteStorage<UiImage>,
+        parents: &mut WriteStorage<Parent>,
+    ) -> OverlayUi {
+        let text_height = LINE_COUNT as f32 * LINE_HEIGHT;
+        let root = entities
+            .build_entity()
+            .with(
+                UiTransform::new(
+                    "debug_overlay".to_string(),
+                    Anchor::TopLeft,
+                    Anchor::TopLeft,
+                    MARGIN,
+                    -MARGIN,
+                    100.,
+                    WIDTH + 2. * MARGIN,
+                    text_height + GRAPH_HEIGHT + 3. * MARGIN,
+                )
+                .into_transparent(),
+                transforms,
+            )
+            .with(UiImage::SolidColor(BACKGROUND), images)
+            .build();
+
+        let lines = (0..LINE_COUNT)
+            .map(|i| {
+                let mut text = UiText::new(font.clone(), String::new(), COLOR_TEXT_LIGHT, 22.);
+                text.align = Anchor::MiddleLeft;
+                entities
+                    .build_entity()
+                    .with(
+                        UiTransform::new(
+                            format!("debug_overlay_line_{}", i),
+                            Anchor::TopLeft,
+                            Anchor::TopLeft,
+                            MARGIN,
+                            -MARGIN - i as f32 * LINE_HEIGHT,
+                            1.,
+                            WIDTH,
+                            LINE_HEIGHT,
+                        )
+                        .into_transparent(),
+                        transforms,
+                    )
+                    .with(text, texts)
+                    .with(Parent { entity: root }, parents)
+                    .build()
+            })
+            .collect();
+
+        let bar_width = WIDTH / GRAPH_SAMPLES as f32;
+        let bars = (0..GRAPH_SAMPLES)
+            .map(|i| {
+                entities
+                    .build_entity()
+                    .with(
+                        UiTransform::new(
+                            format!("debug_overlay_bar_{}", i),
+                            Anchor::BottomLeft,
+                            Anchor::BottomLeft,
+                            MARGIN + i as f32 * bar_width,
+                            MARGIN,
+                            1.,
+                            bar_width,
+                            0.,
+                        )
+                        .into_transparent(),
+                        transforms,
+                    )
+                    .with(UiImage::SolidColor(FAST_FRAME), images)
+                    .with(Parent { entity: root }, parents)
+                    .build()
+            })
+            .collect();
+
+        OverlayUi { root, lines, bars }
+    }
+}
+
+/// Add a frame time to the graph samples, dropping the oldest one when they are all used.
+fn push_sample(frame_times: &mut VecDeque<f32>, frame_time: f32) {
+    if frame_times.len() == GRAPH_SAMPLES {
+        frame_times.pop_front();
+    }
+    frame_times.push_back(frame_time);
+}
+
+/// Height of the graph bar of a frame, cut at `GRAPH_MAX_SECONDS`.
+fn bar_height(frame_time: f32) -> f32 {
+    (frame_time / GRAPH_MAX_SECONDS).min(1.) * GRAPH_HEIGHT
+}
+
+/// Color of the graph bar of a frame: fast at about 60 FPS, slow at about 30 FPS, and dropped
+/// below.
+fn bar_color(frame_time: f32) -> [f32; 4] {
+    if frame_time <= 1. / 55. {
+        FAST_FRAME
+    } else if frame_time <= 1. / 28. {
+        SLOW_FRAME
+    } else {
+        DROPPED_FRAME
+    }
+}
+
+impl<'a> System<'a> for DebugOverlaySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, DebugOverlay>,
+        Read<'a, EventChannel<InputEvent<StringBindings>>>,
+        Read<'a, Time>,
+        Read<'a, FpsCounter>,
+        ReadExpect<'a, Router>,
+        Option<Read<'a, Preloaded>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<FontAsset>>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, SpriteRender>,
+        ReadStorage<'a, Star>,
+        ReadStorage<'a, Pickup>,
+        WriteStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+        WriteStorage<'a, UiImage>,
+        WriteStorage<'a, Parent>,
+    );
+
+    #[instrument(
+        skip(
+            entities,
+            overlay,
+            input_events,
+            time,
+            fps,
+            router,
+            preloaded,
+            loader,
+            fonts,
+            transforms,
+            sprites,
+            stars,
+            pickups,
+            ui_transforms,
+            texts,
+            images,
+            parents
+        ),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut overlay,
+            input_events,
+            time,
+            fps,
+            router,
+            preloaded,
+            loader,
+            fonts,
+            transforms,
+            sprites,
+            stars,
+            pickups,
+            mut ui_transforms,
+            mut texts,
+            mut images,
+            mut parents,
+        ): Self::SystemData,
+    ) {
+        for input_event in input_events.read(&mut self.reader_id) {
+            if let InputEvent::ActionPressed(action) = input_event {
+                if action == bindings::DEBUG_OVERLAY {
+                    overlay.visible = !overlay.visible;
+                    event!(Level::INFO, "Debug overlay visible: {}", overlay.visible);
+                }
+            }
+        }
+
+        if !overlay.visible {
+            if let Some(ui) = self.ui.take() {
+                // the lines and bars are deleted with their parent
+                let _ = entities.delete(ui.root);
+                self.frame_times.clear();
+            }
+            return;
+        }
+
+        if self.ui.is_none() {
+            let font = self
+                .font
+                .get_or_insert_with(|| {
+                    preloaded
+                        .as_ref()
+                        .and_then(|preloaded| preloaded.fonts.get(FONT).cloned())
+                        .unwrap_or_else(|| loader.load(FONT, TtfFormat, (), &fonts))
+                })
+                .clone();
+            self.ui = Some(self.create_ui(
+                &entities,
+                font,
+                &mut ui_transforms,
+                &mut texts,
+                &mut images,
+                &mut parents,
+            ));
+            // show the texts right away
+            self.since_refresh = TEXT_REFRESH;
+        }
+        let ui = self.ui.as_ref().expect("overlay UI is created above");
+
+        let frame_time = time.delta_real_seconds();
+        push_sample(&mut self.frame_times, frame_time);
+        // newest on the right
+        let offset = GRAPH_SAMPLES - self.frame_times.len();
+        for (bar, frame_time) in ui.bars[offset..].iter().zip(&self.frame_times) {
+            if let Some(transform) = ui_transforms.get_mut(*bar) {
+                transform.height = bar_height(*frame_time);
+            }
+            if let Some(image) = images.get_mut(*bar) {
+                *image = UiImage::SolidColor(bar_color(*frame_time));
+            }
+        }
+
+        self.since_refresh += frame_time;
+        if self.since_refresh < TEXT_REFRESH {
+            return;
+        }
+        self.since_refresh = 0.;
+
+        let sampled_fps = fps.sampled_fps();
+        let worst = self.frame_times.iter().copied().fold(0., f32::max);
+        let assets = match preloaded.as_ref() {
+            Some(preloaded) => format!(
+                "Assets: {} fonts, {} textures, {} sounds, {} sprite sheets, {} UI",
+                preloaded.fonts.len(),
+                preloaded.textures.len(),
+                preloaded.sounds.len(),
+                preloaded.sprite_sheets.len(),
+                preloaded.ui.len()
+            ),
+            None => "Assets: not loaded yet".to_string(),
+        };
+        let lines = [
+            format!(
+                "FPS: {:.1} ({:.2} ms, worst {:.2} ms)",
+                sampled_fps,
+                1000. / sampled_fps.max(1.),
+                worst * 1000.
+            ),
+            format!("Entities: {}", (&entities).join().count()),
+            format!(
+                "Transforms: {}  Sprites: {}",
+                (&transforms).join().count(),
+                (&sprites).join().count()
+            ),
+            format!(
+                "UI: {}  Texts: {}  Stars: {}  Pickups: {}",
+                (&ui_transforms).join().count(),
+                (&texts).join().count(),
+                (&stars).join().count(),
+                (&pickups).join().count()
+            ),
+            format!("States: {}", router.screens().join(" > ")),
+            assets,
+        ];
+        for (entity, line) in ui.lines.iter().zip(lines.iter()) {
+            if let Some(text) = texts.get_mut(*entity) {
+                text.text = line.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_keep_the_newest_frames() {
+        let mut frame_times = VecDeque::new();
+        for i in 0..GRAPH_SAMPLES + 3 {
+            push_sample(&mut frame_times, i as f32);
+        }
+        assert_eq!(frame_times.len(), GRAPH_SAMPLES);
+        assert_eq!(frame_times.front(), Some(&3.));
+        assert_eq!(frame_times.back(), Some(&((GRAPH_SAMPLES + 2) as f32)));
+    }
+
+    #[test]
+    fn bars_grow_with_frame_time_up_to_the_graph_height() {
+        assert_eq!(bar_height(0.), 0.);
+        assert!((bar_height(GRAPH_MAX_SECONDS / 2.) - GRAPH_HEIGHT / 2.).abs() < 1e-4);
+        assert_eq!(bar_height(GRAPH_MAX_SECONDS), GRAPH_HEIGHT);
+        assert_eq!(bar_height(1.), GRAPH_HEIGHT);
+    }
+
+    #[test]
+    fn bars_are_colored_by_frame_rate() {
+        assert_eq!(bar_color(1. / 144.), FAST_FRAME);
+        assert_eq!(bar_color(1. / 60.), FAST_FRAME);
+        assert_eq!(bar_color(1. / 45.), SLOW_FRAME);
+        assert_eq!(bar_color(1. / 30.), SLOW_FRAME);
+        assert_eq!(bar_color(1. / 20.), DROPPED_FRAME);
+    }
+}
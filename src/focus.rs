@@ -0,0 +1,302 @@
+use amethyst::{
+    assets::PrefabData,
+    derive::{PrefabData, SystemDesc},
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System, SystemData,
+        World, WorldExt, Write, WriteStorage,
+    },
+    input::{InputEvent, InputHandler, StringBindings},
+    shrev::{EventChannel, ReaderId},
+    ui::{UiEvent, UiEventType},
+    winit::VirtualKeyCode,
+    Error,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, instrument, Level};
+
+use crate::{bindings, console::Console};
+
+/// Marks an UI element that can be reached with the keyboard or a gamepad. Elements are
+/// visited by increasing `order`, ties are broken by creation order.
+///
+/// Prefabs make a widget focusable with the `Focusable` custom widget, the order is then the
+/// `tab_order` of the widget.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+pub struct Focusable {
+    pub order: u32,
+}
+
+impl Focusable {
+    pub fn new(order: u32) -> Self {
+        Self { order }
+    }
+}
+
+impl Component for Focusable {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The currently focused UI element, if any.
+#[derive(Debug, Default)]
+pub struct UiFocus {
+    pub focused: Option<Entity>,
+    /// Ignore navigation, for when a state needs the raw inputs (like when rebinding keys). It is
+    /// also ignored while the `Console` captures the inputs.
+    pub suspended: bool,
+    /// Leave up and down to the state, for when they scroll it. The focus still moves with left,
+    /// right and next.
+    pub horizontal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Navigation {
+    Previous,
+    Next,
+    Activate,
+}
+
+/// Moves the focus between `Focusable` elements with the navigation actions, and activates the
+/// focused element with the confirm action.
+///
+/// Focus changes are sent as `HoverStart` / `HoverStop` UI events and activation as a `Click`
+/// event, so that highlighting and states react the same way as with the mouse.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(UiFocusSystemDesc))]
+pub struct UiFocusSystem {
+    #[system_desc(event_channel_reader)]
+    input_reader_id: ReaderId<InputEvent<StringBindings>>,
+    #[system_desc(event_channel_reader)]
+    ui_reader_id: ReaderId<UiEvent>,
+}
+
+impl UiFocusSystem {
+    pub fn new(
+        input_reader_id: ReaderId<InputEvent<StringBindings>>,
+        ui_reader_id: ReaderId<UiEvent>,
+    ) -> Self {
+        Self {
+            input_reader_id,
+            ui_reader_id,
+        }
+    }
+}
+
+fn navigation(
+    input: &InputEvent<StringBindings>,
+    shift: bool,
+    horizontal: bool,
+) -> Option<Navigation> {
+    match input {
+        InputEvent::ActionPressed(action) => match action.as_str() {
+            bindings::UP | bindings::DOWN if horizontal => None,
+            bindings::UP | bindings::LEFT => Some(Navigation::Previous),
+            bindings::DOWN | bindings::RIGHT => Some(Navigation::Next),
+            bindings::NEXT if shift => Some(Navigation::Previous),
+            bindings::NEXT => Some(Navigation::Next),
+            bindings::CONFIRM => Some(Navigation::Activate),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Element that should get the focus after moving from `current` in `direction`, wrapping
+/// around at both ends. Without a current focus, starts from the first element.
+fn step(ordered: &[Entity], current: Option<Entity>, direction: Navigation) -> Option<Entity> {
+    let position = current.and_then(|current| ordered.iter().position(|e| *e == current));
+    let len = ordered.len();
+    if len == 0 {
+        return None;
+    }
+    let next = match (position, direction) {
+        (None, _) => 0,
+        (Some(i), Navigation::Next) => (i + 1) % len,
+        (Some(i), Navigation::Previous) => (i + len - 1) % len,
+        (Some(i), Navigation::Activate) => i,
+    };
+    Some(ordered[next])
+}
+
+impl<'a> System<'a> for UiFocusSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, EventChannel<InputEvent<StringBindings>>>,
+        Write<'a, EventChannel<UiEvent>>,
+        Read<'a, InputHandler<StringBindings>>,
+        ReadStorage<'a, Focusable>,
+        Write<'a, UiFocus>,
+        Read<'a, Console>,
+    );
+
+    #[instrument(
+        skip(entities, inputs, ui_events, input_handler, focusables, focus, console),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            entities,
+            inputs,
+            mut ui_events,
+            input_handler,
+            focusables,
+            mut focus,
+            console,
+        ): Self::SystemData,
+    ) {
+        // forget about the focused element once it has been removed
+        if let Some(focused) = focus.focused {
+            if !entities.is_alive(focused) || focusables.get(focused).is_none() {
+                focus.focused = None;
+            }
+        }
+
+        // follow the mouse: hovering a focusable element moves the focus to it
+        let hovered = ui_events
+            .read(&mut self.ui_reader_id)
+            .filter(|ev| ev.event_type == UiEventType::HoverStart)
+            .filter(|ev| focusables.get(ev.target).is_some())
+            .map(|ev| ev.target)
+            .last();
+        if let Some(hovered) = hovered {
+            if focus.focused != Some(hovered) {
+                if let Some(previous) = focus.focused {
+                    ui_events.single_write(UiEvent::new(UiEventType::HoverStop, previous));
+                }
+                focus.focused = Some(hovered);
+            }
+        }
+
+        let shift = input_handler.key_is_down(VirtualKeyCode::LShift)
+            || input_handler.key_is_down(VirtualKeyCode::RShift);
+        let navigations = inputs
+            .read(&mut self.input_reader_id)
+            .filter_map(|input| navigation(input, shift, focus.horizontal))
+            .collect::<Vec<_>>();
+        if navigations.is_empty() || focus.suspended || console.captures_input() {
+            return;
+        }
+
+        let mut ordered = (&entities, &focusables)
+            .join()
+            .map(|(entity, focusable)| (focusable.order, entity))
+            .collect::<Vec<_>>();
+        ordered.sort_by_key(|(order, entity)| (*order, entity.id()));
+        let ordered = ordered
+            .into_iter()
+            .map(|(_, entity)| entity)
+            .collect::<Vec<_>>();
+
+        for navigation in navigations {
+            match (navigation, focus.focused) {
+                (Navigation::Activate, Some(focused)) => {
+                    event!(Level::DEBUG, "activating focused element {:?}", focused);
+                    ui_events.single_write(UiEvent::new(UiEventType::Click, focused));
+                }
+                _ => {
+                    let next = step(&ordered, focus.focused, navigation);
+                    if next != focus.focused {
+                        if let Some(previous) = focus.focused {
+                            ui_events.single_write(UiEvent::new(UiEventType::HoverStop, previous));
+                        }
+                        if let Some(next) = next {
+                            ui_events.single_write(UiEvent::new(UiEventType::HoverStart, next));
+                        }
+                        focus.focused = next;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Give the focus to an UI element, highlighting it like when hovered.
+pub fn focus(world: &mut World, entity: Entity) {
+    let previous = world.write_resource::<UiFocus>().focused.replace(entity);
+    let mut events = world.write_resource::<EventChannel<UiEvent>>();
+    if let Some(previous) = previous.filter(|previous| *previous != entity) {
+        events.single_write(UiEvent::new(UiEventType::HoverStop, previous));
+    }
+    events.single_write(UiEvent::new(UiEventType::HoverStart, entity));
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::ecs::{Builder, World, WorldExt};
+
+    use super::*;
+
+    fn elements(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.create_entity().build()).collect()
+    }
+
+    #[test]
+    fn next_wraps_around_to_the_first_element() {
+        let ordered = elements(3);
+        assert_eq!(
+            step(&ordered, Some(ordered[0]), Navigation::Next),
+            Some(ordered[1])
+        );
+        assert_eq!(
+            step(&ordered, Some(ordered[2]), Navigation::Next),
+            Some(ordered[0])
+        );
+    }
+
+    #[test]
+    fn previous_wraps_around_to_the_last_element() {
+        let ordered = elements(3);
+        assert_eq!(
+            step(&ordered, Some(ordered[2]), Navigation::Previous),
+            Some(ordered[1])
+        );
+        assert_eq!(
+            step(&ordered, Some(ordered[0]), Navigation::Previous),
+            Some(ordered[2])
+        );
+    }
+
+    #[test]
+    fn starts_from_the_first_element_without_focus() {
+        let ordered = elements(4);
+        assert_eq!(step(&ordered, None, Navigation::Next), Some(ordered[0]));
+        assert_eq!(step(&ordered, None, Navigation::Previous), Some(ordered[0]));
+        // the focused element is no longer focusable
+        assert_eq!(
+            step(&ordered[..3], Some(ordered[3]), Navigation::Next),
+            Some(ordered[0])
+        );
+    }
+
+    #[test]
+    fn nothing_to_focus_without_elements() {
+        assert_eq!(step(&[], None, Navigation::Next), None);
+    }
+
+    #[test]
+    fn shift_next_goes_to_the_previous_element() {
+        let next = InputEvent::ActionPressed(bindings::NEXT.to_string());
+        assert_eq!(navigation(&next, false, false), Some(Navigation::Next));
+        assert_eq!(navigation(&next, true, false), Some(Navigation::Previous));
+        let up = InputEvent::ActionPressed(bindings::UP.to_string());
+        assert_eq!(navigation(&up, false, false), Some(Navigation::Previous));
+    }
+
+    #[test]
+    fn horizontal_focus_leaves_up_and_down() {
+        let action = |name: &str| InputEvent::ActionPressed(name.to_string());
+        assert_eq!(navigation(&action(bindings::UP), false, true), None);
+        assert_eq!(navigation(&action(bindings::DOWN), false, true), None);
+        assert_eq!(
+            navigation(&action(bindings::LEFT), false, true),
+            Some(Navigation::Previous)
+        );
+        assert_eq!(
+            navigation(&action(bindings::NEXT), false, true),
+            Some(Navigation::Next)
+        );
+    }
+}
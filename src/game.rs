@@ -1,51 +1,67 @@
-use crate::pause::PauseMenuState;
+use crate::{
+    bindings,
+    camera::{self, CameraConfig, CameraController},
+    console,
+    gamepad::is_controller_disconnected,
+    gameplay::{self, GameplayConfig, GameplayDispatcher, GameplayState, Run, TimeScale},
+    hud, router,
+    save::{self, PendingLoad},
+    settings::UserSettings,
+    starfield,
+    window_focus::is_focus_lost,
+};
 
 use amethyst::{
-    audio::output::init_output,
-    core::Time,
     ecs::prelude::{Entity, WorldExt},
-    input::{is_close_requested, is_key_down},
+    input::is_close_requested,
     prelude::*,
-    ui::{UiCreator, UiFinder, UiText},
-    utils::fps_counter::FpsCounter,
-    winit::VirtualKeyCode,
 };
 
 use tracing::{event, Level};
 
-/// Main 'Game' state. Actually, it is mostly similar to the ui/main.rs content-wise.
-/// The main differences include the added 'paused' field in the state, which is toggled when
-/// 'pausing'.
-
+/// Main 'Game' state. It runs the gameplay systems, which stop when another state is pushed over
+/// it, and leaves when the run is over.
 #[derive(Default)]
 pub struct Game {
-    // If the Game is paused or not
-    paused: bool,
+    // The gameplay systems, only dispatched while this state is active
+    gameplay: Option<GameplayDispatcher>,
+    // The camera showing the sprites of the game
+    camera: Option<Entity>,
     // The UI root entity. Deleting this should remove the complete UI
     ui_root: Option<Entity>,
-    // A reference to the FPS display, which we want to interact with
-    fps_display: Option<Entity>,
-    // A reference to the random text, which we want to modify during updates
-    random_text: Option<Entity>,
 }
 
 impl SimpleState for Game {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        let StateData { mut world, .. } = data;
+        let StateData { world, .. } = data;
+
+        // first, so that the gameplay components are registered before restoring a save
+        self.gameplay = Some(GameplayDispatcher::new(world));
+
+        let duration = world.read_resource::<GameplayConfig>().duration;
+        world.insert(Run::new(duration));
+        // slow motion and stepping are debug tools of one game
+        world.insert(TimeScale::default());
+        match world.remove::<PendingLoad>() {
+            Some(PendingLoad(save)) => save::restore(world, save),
+            None => save::clear(world),
+        }
+        gameplay::populate(world);
+        gameplay::register_commands(world);
+        *world.write_resource::<GameplayState>() = GameplayState::Running;
 
-        // needed for registering audio output.
-        init_output(&mut world);
+        self.camera = Some(camera::spawn(world));
+        starfield::spawn(world);
 
-        self.ui_root =
-            Some(world.exec(|mut creator: UiCreator<'_>| creator.create("ui/example.ron", ())));
+        self.ui_root = Some(hud::create(world));
     }
 
-    fn on_pause(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
-        self.paused = true;
+    fn on_pause(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<GameplayState>() = GameplayState::Paused;
     }
 
-    fn on_resume(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
-        self.paused = false;
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<GameplayState>() = GameplayState::Running;
     }
 
     fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
@@ -54,90 +70,119 @@ impl SimpleState for Game {
                 .delete_entity(root_entity)
                 .expect("Failed to remove Game Screen");
         }
+        if let Some(camera) = self.camera {
+            data.world
+                .delete_entity(camera)
+                .expect("Failed to remove Game camera");
+        }
+        starfield::clear(data.world);
+        save::clear(data.world);
+        gameplay::unregister_commands(data.world);
+        *data.world.write_resource::<GameplayState>() = GameplayState::Paused;
 
+        self.gameplay = None;
+        self.camera = None;
         self.ui_root = None;
-        self.fps_display = None;
-        self.random_text = None;
     }
 
     fn handle_event(
         &mut self,
-        _: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
         match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
             StateEvent::Window(event) => {
                 if is_close_requested(&event) {
                     event!(Level::INFO, "[Trans::Quit] Quitting Application!");
                     Trans::Quit
-                } else if is_key_down(&event, VirtualKeyCode::Escape) {
-                    event!(Level::INFO, "[Trans::Push] Pausing Game!");
-                    Trans::Push(Box::new(PauseMenuState::default()))
+                } else if is_focus_lost(&event)
+                    && data.world.read_resource::<UserSettings>().unfocused.pause
+                {
+                    event!(Level::INFO, "Window lost focus, pausing Game!");
+                    router::go(data.world, "pause")
                 } else {
                     Trans::None
                 }
             }
+            StateEvent::Input(_)
+                if bindings::is_action(&event, bindings::PAUSE)
+                    || bindings::is_action(&event, bindings::BACK) =>
+            {
+                event!(Level::INFO, "Pausing Game!");
+                router::go(data.world, "pause")
+            }
+            StateEvent::Input(_) if is_controller_disconnected(&event) => {
+                event!(Level::INFO, "Controller lost, pausing Game!");
+                router::go(data.world, "pause")
+            }
+            StateEvent::Input(_)
+                if bindings::is_action(&event, bindings::ZOOM_IN)
+                    || bindings::is_action(&event, bindings::ZOOM_OUT) =>
+            {
+                let config = data.world.read_resource::<CameraConfig>();
+                let factor = if bindings::is_action(&event, bindings::ZOOM_IN) {
+                    config.zoom_step
+                } else {
+                    1. / config.zoom_step
+                };
+                if let Some(camera) = self.camera {
+                    if let Some(controller) = data
+                        .world
+                        .write_storage::<CameraController>()
+                        .get_mut(camera)
+                    {
+                        controller.zoom_by(factor, &config);
+                    }
+                }
+                Trans::None
+            }
+            StateEvent::Input(_)
+                if cfg!(debug_assertions) && bindings::is_action(&event, bindings::SLOW_MOTION) =>
+            {
+                data.world
+                    .write_resource::<TimeScale>()
+                    .toggle_slow_motion();
+                Trans::None
+            }
+            StateEvent::Input(_)
+                if cfg!(debug_assertions) && bindings::is_action(&event, bindings::FRAME_STEP) =>
+            {
+                data.world.write_resource::<TimeScale>().toggle_stepping();
+                Trans::None
+            }
+            StateEvent::Input(_)
+                if cfg!(debug_assertions) && bindings::is_action(&event, bindings::STEP) =>
+            {
+                data.world.write_resource::<TimeScale>().step();
+                Trans::None
+            }
+            // every mouse move, kept out of the console scrollback
             StateEvent::Ui(ui_event) => {
                 event!(
-                    Level::INFO,
+                    Level::TRACE,
                     "[HANDLE_EVENT] You just interacted with a ui element: {:?}",
                     ui_event
                 );
                 Trans::None
             }
             StateEvent::Input(input) => {
-                event!(Level::INFO, "Input Event detected: {:?}.", input);
+                event!(Level::TRACE, "Input Event detected: {:?}.", input);
                 Trans::None
             }
         }
     }
 
-    fn update(&mut self, state_data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        let StateData { world, .. } = state_data;
-
-        // this cannot happen in 'on_start', as the entity might not be fully
-        // initialized/registered/created yet.
-        if self.fps_display.is_none() {
-            world.exec(|finder: UiFinder<'_>| {
-                if let Some(entity) = finder.find("fps") {
-                    self.fps_display = Some(entity);
-                }
-            });
-        }
-
-        if self.random_text.is_none() {
-            world.exec(|finder: UiFinder| {
-                if let Some(entity) = finder.find("random_text") {
-                    self.random_text = Some(entity);
-                }
-            });
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if let Some(gameplay) = self.gameplay.as_mut() {
+            gameplay.dispatch(data.world);
         }
+        starfield::refill_if_resized(data.world);
 
-        // it is important that the 'paused' field is actually pausing your game.
-        // Make sure to also pause your running systems.
-        if !self.paused {
-            let mut ui_text = world.write_storage::<UiText>();
-
-            if let Some(fps_display) = self.fps_display.and_then(|entity| ui_text.get_mut(entity)) {
-                if world.read_resource::<Time>().frame_number() % 20 == 0 && !self.paused {
-                    let fps = world.read_resource::<FpsCounter>().sampled_fps();
-                    fps_display.text = format!("FPS: {:.*}", 2, fps);
-                }
-            }
-
-            if let Some(random_text) = self.random_text.and_then(|entity| ui_text.get_mut(entity)) {
-                if let Ok(value) = random_text.text.parse::<i32>() {
-                    let mut new_value = value * 10;
-                    if new_value > 100_000 {
-                        new_value = 1;
-                    }
-                    random_text.text = new_value.to_string();
-                } else {
-                    random_text.text = String::from("1");
-                }
-            }
+        if data.world.read_resource::<Run>().over {
+            router::go(data.world, "game_over")
+        } else {
+            Trans::None
         }
-
-        Trans::None
     }
 }
@@ -0,0 +1,416 @@
+use amethyst::{
+    core::transform::Parent,
+    ecs::prelude::Entity,
+    input::is_close_requested,
+    prelude::*,
+    ui::{Anchor, Stretch, UiImage, UiText, UiTransform},
+    winit::{Event, WindowEvent},
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console,
+    focus::UiFocus,
+    gameplay::Run,
+    high_scores::{HighScore, HighScores, MAX_NAME_LENGTH},
+    hud::format_time,
+    i18n::Translations,
+    router,
+    toast::Toasts,
+    ui_scheme::{load_font, Menu, MenuButton, COLOR_3, COLOR_TEXT_LIGHT},
+};
+
+// center of the first line of text, the buttons are below them
+const LINES_TOP: f32 = 320.;
+const LINE_SPACING: f32 = 12.;
+// semi transparent, to still see the game below
+const BACKGROUND: [f32; 4] = [0., 0., 0., 0.6];
+const DEFAULT_NAME: &str = "Player";
+
+/// A line of text, with its font size and color.
+type Line = (String, f32, [f32; 4]);
+
+/// Results of the run that just ended, displayed over the game with the choice to play again or
+/// to go back to the main menu. When the run makes it to the high scores, the player is first
+/// asked for their name.
+#[derive(Debug, Default)]
+pub struct GameOverScreen {
+    ui_root: Option<Entity>,
+    run: Run,
+    // best score before this run
+    previous_best: u32,
+    // name being entered, with the text displaying it
+    name_entry: Option<(String, Entity)>,
+}
+
+impl GameOverScreen {
+    fn delete_ui(&mut self, world: &mut World) {
+        if let Some(root_entity) = self.ui_root.take() {
+            world
+                .delete_entity(root_entity)
+                .expect("Failed to remove GameOverScreen");
+        }
+    }
+
+    /// Display `lines` from `LINES_TOP` down, returning the entity of each line.
+    fn create_lines(world: &mut World, parent: Entity, lines: Vec<Line>) -> Vec<Entity> {
+        let font = load_font(world, "font/mandrill.ttf");
+        let sizes = lines.iter().map(|line| line.1).collect::<Vec<_>>();
+        let positions = line_positions(&sizes);
+        let mut entities = vec![];
+        for (i, ((text, font_size, color), y)) in lines.into_iter().zip(positions).enumerate() {
+            entities.push(
+                world
+                    .create_entity()
+                    .with(UiTransform::new(
+                        format!("game_over_line_{}", i),
+                        Anchor::Middle,
+                        Anchor::Middle,
+                        0.,
+                        y,
+                        3.,
+                        1000.,
+                        font_size * 1.2,
+                    ))
+                    .with(UiText::new(font.clone(), text, color, font_size))
+                    .with(Parent { entity: parent })
+                    .build(),
+            );
+        }
+        entities
+    }
+
+    /// Ask for the name to put in the high scores, starting from the last one entered.
+    fn show_name_entry(&mut self, world: &mut World) {
+        let name = world.read_resource::<HighScores>().last_name.clone();
+        let lines = {
+            let translations = world.read_resource::<Translations>();
+            vec![
+                (translations.tr("New high score!").to_string(), 70., COLOR_3),
+                (
+                    format!("{} {}", translations.tr("Score"), self.run.score),
+                    44.,
+                    COLOR_TEXT_LIGHT,
+                ),
+                (
+                    translations.tr("Enter your name").to_string(),
+                    36.,
+                    COLOR_TEXT_LIGHT,
+                ),
+                (format!("{}_", name), 60., COLOR_3),
+                (
+                    translations.tr("Press confirm when done").to_string(),
+                    30.,
+                    COLOR_TEXT_LIGHT,
+                ),
+            ]
+        };
+
+        let root = world
+            .create_entity()
+            .with(
+                UiTransform::new(
+                    "game_over_name_entry".to_string(),
+                    Anchor::Middle,
+                    Anchor::Middle,
+                    0.,
+                    0.,
+                    2.,
+                    20.,
+                    20.,
+                )
+                .with_stretch(Stretch::XY {
+                    x_margin: 0.,
+                    y_margin: 0.,
+                    keep_aspect_ratio: false,
+                }),
+            )
+            .with(UiImage::SolidColor(BACKGROUND))
+            .build();
+        let lines = Self::create_lines(world, root, lines);
+        self.ui_root = Some(root);
+        self.name_entry = Some((name, lines[3]));
+        // typed keys are for the name, not for navigating
+        world.write_resource::<UiFocus>().suspended = true;
+    }
+
+    fn edit_name(&mut self, world: &World, character: char) {
+        let (name, text) = match self.name_entry.as_mut() {
+            Some(entry) => entry,
+            None => return,
+        };
+        if !type_character(name, character) {
+            return;
+        }
+        if let Some(text) = world.write_storage::<UiText>().get_mut(*text) {
+            text.text = format!("{}_", name);
+        }
+    }
+
+    /// Put the run in the high scores with the entered name, then show the results.
+    fn submit_name(&mut self, world: &mut World) {
+        let name = match self.name_entry.take() {
+            Some((name, _)) if !name.is_empty() => name,
+            _ => DEFAULT_NAME.to_string(),
+        };
+        world.write_resource::<UiFocus>().suspended = false;
+
+        let entry = HighScore::new(&name, &self.run);
+        let rank = world.write_resource::<HighScores>().insert(entry);
+        event!(
+            Level::INFO,
+            "{} entered the high scores at {:?}",
+            name,
+            rank
+        );
+        if let Err(err) = world.read_resource::<HighScores>().save() {
+            event!(Level::ERROR, "Error saving high scores: {}", err);
+            world
+                .write_resource::<Toasts>()
+                .show("High scores could not be saved");
+        }
+
+        self.delete_ui(world);
+        self.show_results(world);
+    }
+
+    fn show_results(&mut self, world: &mut World) {
+        let lines = {
+            let translations = world.read_resource::<Translations>();
+            vec![
+                (translations.tr("Game Over").to_string(), 70., COLOR_3),
+                (
+                    format!("{} {}", translations.tr("Score"), self.run.score),
+                    44.,
+                    COLOR_TEXT_LIGHT,
+                ),
+                (
+                    format!(
+                        "{} {}",
+                        translations.tr("Time"),
+                        format_time(self.run.elapsed)
+                    ),
+                    36.,
+                    COLOR_TEXT_LIGHT,
+                ),
+                if self.run.score > self.previous_best {
+                    (translations.tr("New best score!").to_string(), 36., COLOR_3)
+                } else {
+                    (
+                        format!("{} {}", translations.tr("Best"), self.previous_best),
+                        36.,
+                        COLOR_TEXT_LIGHT,
+                    )
+                },
+            ]
+        };
+
+        let menu = Menu {
+            background: Some(BACKGROUND),
+            button_size: (800., 80.),
+            ..Menu::new(
+                "game_over",
+                vec![
+                    MenuButton::new("Retry", "retry", UiAction::Retry),
+                    MenuButton::new("Main Menu", "main_menu", UiAction::ExitToMenu),
+                ],
+            )
+        };
+        let root = menu.create(world);
+        Self::create_lines(world, root, lines);
+        self.ui_root = Some(root);
+    }
+}
+
+/// Vertical position of the center of each line, from `LINES_TOP` down, for lines of these font
+/// sizes.
+fn line_positions(sizes: &[f32]) -> Vec<f32> {
+    let mut y = LINES_TOP;
+    let mut positions = vec![];
+    for (i, font_size) in sizes.iter().enumerate() {
+        positions.push(y);
+        let next_size = sizes.get(i + 1).copied().unwrap_or(0.);
+        y -= (font_size + next_size) * 0.6 + LINE_SPACING;
+    }
+    positions
+}
+
+/// Apply a typed character to a name, returning whether it changed. Backspace removes the last
+/// character, and only characters that are safe in a file are accepted.
+fn type_character(name: &mut String, character: char) -> bool {
+    match character {
+        // backspace
+        '\u{8}' => name.pop().is_some(),
+        c if (c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+            && name.chars().count() < MAX_NAME_LENGTH =>
+        {
+            name.push(c);
+            true
+        }
+        _ => false,
+    }
+}
+
+impl SimpleState for GameOverScreen {
+    #[instrument(skip(data), level = "info")]
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        self.run = world.read_resource::<Run>().clone();
+        let qualifies = {
+            let high_scores = world.read_resource::<HighScores>();
+            self.previous_best = high_scores.best().map_or(0, |best| best.score);
+            high_scores.qualifies(self.run.score)
+        };
+        event!(
+            Level::INFO,
+            "Game over: score {}, best {}",
+            self.run.score,
+            self.previous_best
+        );
+
+        if qualifies {
+            self.show_name_entry(world);
+        } else {
+            self.show_results(world);
+        }
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
+            StateEvent::Window(event) if is_close_requested(&event) => {
+                event!(Level::INFO, "Quitting Application!");
+                Trans::Quit
+            }
+            StateEvent::Window(Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(character),
+                ..
+            }) if self.name_entry.is_some() => {
+                self.edit_name(data.world, *character);
+                Trans::None
+            }
+            // not on back, it is also backspace
+            StateEvent::Input(_)
+                if self.name_entry.is_some() && bindings::is_action(&event, bindings::CONFIRM) =>
+            {
+                self.submit_name(data.world);
+                Trans::None
+            }
+            _ if self.name_entry.is_some() => Trans::None,
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::BACK) => {
+                router::go(data.world, router::BACK)
+            }
+            StateEvent::Ui(_) => match ui_action(data.world, &event) {
+                Some(UiAction::Retry) => router::go(data.world, "retry"),
+                Some(UiAction::ExitToMenu) => router::go(data.world, "exit_to_menu"),
+                _ => Trans::None,
+            },
+            _ => Trans::None,
+        }
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        self.delete_ui(data.world);
+        self.name_entry = None;
+        data.world.write_resource::<UiFocus>().suspended = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::{input::InputEvent, winit::WindowId, DataInit};
+
+    use super::*;
+
+    fn typed(name: &str, characters: &str) -> String {
+        let mut name = name.to_string();
+        for character in characters.chars() {
+            type_character(&mut name, character);
+        }
+        name
+    }
+
+    #[test]
+    fn name_takes_safe_characters_only() {
+        assert_eq!(typed("", "Ann-3_b.c"), "Ann-3_b.c");
+        assert_eq!(typed("", "a b/c\\d\n\t"), "abcd");
+        assert_eq!(typed("", "Zoë"), "Zoë");
+        assert!(!type_character(&mut "a".to_string(), ' '));
+    }
+
+    #[test]
+    fn backspace_removes_the_last_character() {
+        assert_eq!(typed("Player", "\u{8}\u{8}"), "Play");
+        let mut empty = String::new();
+        assert!(!type_character(&mut empty, '\u{8}'));
+        assert_eq!(typed("Zoë", "\u{8}"), "Zo");
+    }
+
+    #[test]
+    fn name_length_is_capped() {
+        let long = "x".repeat(MAX_NAME_LENGTH + 5);
+        assert_eq!(typed("", &long).chars().count(), MAX_NAME_LENGTH);
+        // counted in characters, not bytes
+        let accents = "é".repeat(MAX_NAME_LENGTH);
+        assert_eq!(typed("", &accents), accents);
+        assert_eq!(
+            typed(&accents, "\u{8}a"),
+            format!("{}a", "é".repeat(MAX_NAME_LENGTH - 1))
+        );
+    }
+
+    #[test]
+    fn lines_go_down_without_overlapping() {
+        let sizes = [70., 44., 36.];
+        let positions = line_positions(&sizes);
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0], LINES_TOP);
+        for i in 1..sizes.len() {
+            let gap = positions[i - 1] - positions[i];
+            // from center to center, each line takes at least half of its height
+            assert!(gap >= (sizes[i - 1] + sizes[i]) / 2. + LINE_SPACING);
+        }
+        assert!(line_positions(&[]).is_empty());
+    }
+
+    #[test]
+    fn backspace_edits_the_name_without_submitting_it() {
+        let mut world = World::new();
+        world.register::<UiText>();
+        let mut game_data = GameDataBuilder::default().build(&mut world);
+        let text = world.create_entity().build();
+        let mut screen = GameOverScreen {
+            name_entry: Some(("Anne".to_string(), text)),
+            ..Default::default()
+        };
+
+        // backspace is typed, then pressed as the back action
+        let events = vec![
+            StateEvent::Window(Event::WindowEvent {
+                window_id: unsafe { WindowId::dummy() },
+                event: WindowEvent::ReceivedCharacter('\u{8}'),
+            }),
+            StateEvent::Input(InputEvent::ActionPressed(bindings::BACK.to_string())),
+        ];
+        for event in events {
+            let trans = screen.handle_event(StateData::new(&mut world, &mut game_data), event);
+            assert!(match trans {
+                Trans::None => true,
+                _ => false,
+            });
+        }
+        assert_eq!(
+            screen.name_entry.map(|(name, _)| name),
+            Some("Ann".to_string())
+        );
+    }
+}
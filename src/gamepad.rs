@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    core::math::Vector2,
+    derive::SystemDesc,
+    ecs::prelude::{System, SystemData, Write},
+    input::{ControllerAxis, ControllerEvent, InputEvent, InputHandler, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::bindings;
+
+// a stick is pushed in a direction past this value, and released under the other
+const STICK_PRESS: f32 = 0.5;
+const STICK_RELEASE: f32 = 0.3;
+// stick positions closer to the center are read as centered, sticks rarely rest at zero
+const STICK_DEAD_ZONE: f32 = 0.2;
+
+/// Controllers currently connected.
+#[derive(Debug, Default)]
+pub struct Gamepads {
+    pub connected: Vec<u32>,
+}
+
+/// Connects controllers to the named actions:
+/// * forwards the `ControllerEvent`s written to its channel to the `InputHandler`. Real
+///   controllers are handled by amethyst with the `sdl_controller` feature, anything else can
+///   write there to simulate one,
+/// * turns the left stick into the navigation actions,
+/// * keeps `Gamepads` up to date.
+///
+/// Buttons are mapped to actions in `config/bindings.ron`, like Start to pause the game.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(GamepadSystemDesc))]
+pub struct GamepadSystem {
+    #[system_desc(event_channel_reader)]
+    controller_reader_id: ReaderId<ControllerEvent>,
+    #[system_desc(event_channel_reader)]
+    input_reader_id: ReaderId<InputEvent<StringBindings>>,
+    #[system_desc(skip)]
+    sticks: HashMap<(u32, ControllerAxis), i8>,
+}
+
+impl GamepadSystem {
+    pub fn new(
+        controller_reader_id: ReaderId<ControllerEvent>,
+        input_reader_id: ReaderId<InputEvent<StringBindings>>,
+    ) -> Self {
+        Self {
+            controller_reader_id,
+            input_reader_id,
+            sticks: HashMap::new(),
+        }
+    }
+
+    /// Action to trigger when a stick axis moves, if it just got pushed in a direction.
+    fn stick_action(
+        &mut self,
+        which: u32,
+        axis: ControllerAxis,
+        value: f32,
+    ) -> Option<&'static str> {
+        let direction = self.sticks.entry((which, axis)).or_insert(0);
+        let new_direction = if value.abs() > STICK_PRESS {
+            value.signum() as i8
+        } else if value.abs() < STICK_RELEASE {
+            0
+        } else {
+            *direction
+        };
+        if new_direction == *direction {
+            return None;
+        }
+        *direction = new_direction;
+        // sdl axis are positive towards the bottom
+        match (axis, new_direction) {
+            (ControllerAxis::LeftX, -1) => Some(bindings::LEFT),
+            (ControllerAxis::LeftX, 1) => Some(bindings::RIGHT),
+            (ControllerAxis::LeftY, -1) => Some(bindings::UP),
+            (ControllerAxis::LeftY, 1) => Some(bindings::DOWN),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> System<'a> for GamepadSystem {
+    type SystemData = (
+        Write<'a, EventChannel<ControllerEvent>>,
+        Write<'a, EventChannel<InputEvent<StringBindings>>>,
+        Write<'a, InputHandler<StringBindings>>,
+        Write<'a, Gamepads>,
+    );
+
+    #[instrument(
+        skip(controller_events, input_events, input_handler, gamepads),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (controller_events, mut input_events, mut input_handler, mut gamepads): Self::SystemData,
+    ) {
+        for controller_event in controller_events.read(&mut self.controller_reader_id) {
+            input_handler.send_controller_event(controller_event, &mut input_events);
+        }
+
+        let inputs = input_events
+            .read(&mut self.input_reader_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut actions = vec![];
+        for input in &inputs {
+            match input {
+                InputEvent::ControllerConnected { which } => {
+                    event!(Level::INFO, "Controller {} connected", which);
+                    if !gamepads.connected.contains(which) {
+                        gamepads.connected.push(*which);
+                    }
+                }
+                InputEvent::ControllerDisconnected { which } => {
+                    event!(Level::INFO, "Controller {} disconnected", which);
+                    gamepads.connected.retain(|connected| connected != which);
+                    self.sticks
+                        .retain(|(stick_which, _), _| stick_which != which);
+                }
+                InputEvent::ControllerAxisMoved { which, axis, value } => {
+                    if let Some(action) = self.stick_action(*which, *axis, *value) {
+                        actions.push(action);
+                    }
+                }
+                _ => (),
+            }
+        }
+        for action in actions {
+            input_events.single_write(InputEvent::ActionPressed(action.to_string()));
+        }
+    }
+}
+
+/// Position of the left sticks of the connected controllers, added together up to a length of
+/// one. Up is positive.
+pub fn left_stick(input: &InputHandler<StringBindings>) -> Vector2<f32> {
+    let mut position = Vector2::new(0., 0.);
+    for which in input.connected_controllers() {
+        let axis = |axis| input.controller_axis_value(which, axis).unwrap_or(0.);
+        // sdl axis are positive towards the bottom
+        let stick = Vector2::new(axis(ControllerAxis::LeftX), -axis(ControllerAxis::LeftY));
+        if stick.norm() > STICK_DEAD_ZONE {
+            position += stick;
+        }
+    }
+    if position.norm() > 1. {
+        position.normalize()
+    } else {
+        position
+    }
+}
+
+/// Is this event a controller being disconnected.
+pub fn is_controller_disconnected(event: &StateEvent) -> bool {
+    match event {
+        StateEvent::Input(InputEvent::ControllerDisconnected { .. }) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::{
+        core::SystemDesc,
+        ecs::{RunNow, World, WorldExt},
+        input::ControllerButton,
+    };
+
+    use super::*;
+
+    struct Simulation {
+        world: World,
+        system: GamepadSystem,
+        reader_id: ReaderId<InputEvent<StringBindings>>,
+    }
+
+    impl Simulation {
+        fn new() -> Self {
+            let mut world = World::new();
+            let system = GamepadSystemDesc::default().build(&mut world);
+            world
+                .write_resource::<InputHandler<StringBindings>>()
+                .bindings = bindings::load_defaults().expect("default bindings are valid");
+            let reader_id = world
+                .write_resource::<EventChannel<InputEvent<StringBindings>>>()
+                .register_reader();
+            Self {
+                world,
+                system,
+                reader_id,
+            }
+        }
+
+        /// Send the controller events through the system, returning the actions pressed.
+        fn send(&mut self, controller_events: Vec<ControllerEvent>) -> Vec<String> {
+            self.world
+                .write_resource::<EventChannel<ControllerEvent>>()
+                .iter_write(controller_events);
+            self.system.run_now(&self.world);
+            self.world
+                .read_resource::<EventChannel<InputEvent<StringBindings>>>()
+                .read(&mut self.reader_id)
+                .filter_map(|input| match input {
+                    InputEvent::ActionPressed(action) => Some(action.clone()),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        fn connected(&self) -> Vec<u32> {
+            self.world.read_resource::<Gamepads>().connected.clone()
+        }
+
+        fn left_stick(&self) -> Vector2<f32> {
+            left_stick(&self.world.read_resource::<InputHandler<StringBindings>>())
+        }
+    }
+
+    #[test]
+    fn tracks_connected_controllers() {
+        let mut simulation = Simulation::new();
+        simulation.send(vec![
+            ControllerEvent::ControllerConnected { which: 0 },
+            ControllerEvent::ControllerConnected { which: 1 },
+        ]);
+        assert_eq!(simulation.connected(), vec![0, 1]);
+        simulation.send(vec![ControllerEvent::ControllerDisconnected { which: 0 }]);
+        assert_eq!(simulation.connected(), vec![1]);
+    }
+
+    #[test]
+    fn buttons_trigger_their_actions_on_any_controller() {
+        let mut simulation = Simulation::new();
+        simulation.send(vec![ControllerEvent::ControllerConnected { which: 2 }]);
+        let actions = simulation.send(vec![ControllerEvent::ControllerButtonPressed {
+            which: 2,
+            button: ControllerButton::Start,
+        }]);
+        assert_eq!(actions, vec![bindings::PAUSE.to_string()]);
+        let actions = simulation.send(vec![ControllerEvent::ControllerButtonPressed {
+            which: 2,
+            button: ControllerButton::A,
+        }]);
+        assert_eq!(actions, vec![bindings::CONFIRM.to_string()]);
+    }
+
+    #[test]
+    fn left_stick_navigates_once_per_push() {
+        let mut simulation = Simulation::new();
+        simulation.send(vec![ControllerEvent::ControllerConnected { which: 0 }]);
+        let axis = |value| ControllerEvent::ControllerAxisMoved {
+            which: 0,
+            axis: ControllerAxis::LeftY,
+            value,
+        };
+        assert_eq!(
+            simulation.send(vec![axis(0.8)]),
+            vec![bindings::DOWN.to_string()]
+        );
+        // still pushed, or not released enough
+        assert!(simulation.send(vec![axis(0.9), axis(0.4)]).is_empty());
+        assert!(simulation.send(vec![axis(0.1)]).is_empty());
+        assert_eq!(
+            simulation.send(vec![axis(-0.8)]),
+            vec![bindings::UP.to_string()]
+        );
+    }
+
+    #[test]
+    fn left_stick_is_held_with_a_dead_zone() {
+        let mut simulation = Simulation::new();
+        simulation.send(vec![ControllerEvent::ControllerConnected { which: 0 }]);
+        let axis = |axis, value| ControllerEvent::ControllerAxisMoved {
+            which: 0,
+            axis,
+            value,
+        };
+        simulation.send(vec![axis(ControllerAxis::LeftX, 0.1)]);
+        assert_eq!(simulation.left_stick(), Vector2::new(0., 0.));
+
+        simulation.send(vec![
+            axis(ControllerAxis::LeftX, 0.6),
+            axis(ControllerAxis::LeftY, -0.4),
+        ]);
+        assert_eq!(simulation.left_stick(), Vector2::new(0.6, 0.4));
+        // still held on the next frames, without new events
+        simulation.send(vec![]);
+        assert_eq!(simulation.left_stick(), Vector2::new(0.6, 0.4));
+
+        simulation.send(vec![
+            axis(ControllerAxis::LeftX, 1.),
+            axis(ControllerAxis::LeftY, 1.),
+        ]);
+        assert!((simulation.left_stick().norm() - 1.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,447 @@
+use amethyst::{
+    assets::Handle,
+    core::{math::Vector3, ArcThreadPool, Time, Transform},
+    ecs::prelude::{
+        Dispatcher, DispatcherBuilder, Entities, Join, Read, ReadStorage, System, World, WorldExt,
+        Write, WriteStorage,
+    },
+    prelude::Builder,
+    renderer::{palette::Srgba, resources::Tint, SpriteRender, SpriteSheet},
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+    camera::{Bounds, CameraTarget},
+    console::{ArgKind, Commands, Param},
+    hud::HudSystem,
+    loading::Preloaded,
+    pickup::{Pickup, PickupSystem},
+    player::{Player, PlayerSystem},
+    save::{Playtime, Saved},
+};
+
+// time scale used for slow motion
+const SLOW_MOTION: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 10.;
+const MAX_SPAWN: i64 = 1000;
+// console commands available while the game state runs
+const COMMANDS: &[&str] = &["spawn", "timescale"];
+
+/// Rules of the game, from `config/gameplay.ron`. Distances are in world units.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GameplayConfig {
+    /// Length of a run, in seconds.
+    pub duration: f32,
+    /// The player and the stars stay inside it.
+    pub arena: Bounds,
+    /// Sprite sheet of the player and the stars, as listed in the asset manifest.
+    pub sheet: String,
+    pub player_sprite: usize,
+    pub player_size: f32,
+    /// Distance moved per second.
+    pub player_speed: f32,
+    /// Stars to collect in the arena at any time.
+    pub star_count: usize,
+    pub star_size: f32,
+    pub star_score: u32,
+    /// Camera shake when a star is collected.
+    pub collect_shake: f32,
+}
+
+impl Default for GameplayConfig {
+    fn default() -> Self {
+        Self {
+            duration: 60.,
+            arena: Bounds {
+                min: (-1024., -768.),
+                max: (1024., 768.),
+            },
+            sheet: "sprites/stars.ron".to_string(),
+            player_sprite: 0,
+            player_size: 64.,
+            player_speed: 500.,
+            star_count: 10,
+            star_size: 32.,
+            star_score: 10,
+            collect_shake: 0.3,
+        }
+    }
+}
+
+/// The current run. It is over when there is no time left.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Run {
+    pub score: u32,
+    pub collected: u32,
+    /// Time left, in seconds.
+    pub remaining: f32,
+    /// Time played, in seconds.
+    pub elapsed: f32,
+    pub over: bool,
+}
+
+impl Run {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            remaining: duration,
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether the gameplay is running, for anything that needs to react to pauses outside of the
+/// gameplay systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameplayState {
+    Running,
+    Paused,
+}
+
+impl Default for GameplayState {
+    fn default() -> Self {
+        GameplayState::Paused
+    }
+}
+
+/// Speed of the gameplay relative to real time. Gameplay systems should use `TimeScale::delta`
+/// instead of the `Time` deltas.
+#[derive(Debug, Clone)]
+pub struct TimeScale {
+    pub scale: f32,
+    /// Only run the gameplay one frame at a time, when requested with `step`.
+    pub stepping: bool,
+    step_requested: bool,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            stepping: false,
+            step_requested: false,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Gameplay time elapsed during this frame, in seconds.
+    pub fn delta(&self, time: &Time) -> f32 {
+        time.delta_seconds() * self.scale
+    }
+
+    pub fn toggle_slow_motion(&mut self) {
+        self.scale = if self.scale < 1. { 1. } else { SLOW_MOTION };
+        event!(Level::INFO, "Time scale: {}", self.scale);
+    }
+
+    pub fn toggle_stepping(&mut self) {
+        self.stepping = !self.stepping;
+        self.step_requested = false;
+        event!(Level::INFO, "Frame stepping: {}", self.stepping);
+    }
+
+    /// Run the gameplay for one frame while stepping.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// Should the gameplay systems run this frame, consuming the requested step if stepping.
+    fn advance(&mut self) -> bool {
+        if !self.stepping {
+            true
+        } else if self.step_requested {
+            self.step_requested = false;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Insert the resources controlling the gameplay, read by screens before it first runs.
+pub fn init(world: &mut World) {
+    world.insert(GameplayState::default());
+    world.insert(TimeScale::default());
+}
+
+/// Counts the time spent playing.
+#[derive(Debug, Default)]
+pub struct PlaytimeSystem;
+
+impl<'a> System<'a> for PlaytimeSystem {
+    type SystemData = (Read<'a, Time>, Read<'a, TimeScale>, Write<'a, Playtime>);
+
+    fn run(&mut self, (time, time_scale, mut playtime): Self::SystemData) {
+        playtime.0 += f64::from(time_scale.delta(&time));
+    }
+}
+
+/// Counts down the time left in the `Run`, and ends it.
+#[derive(Debug, Default)]
+pub struct RunSystem;
+
+impl<'a> System<'a> for RunSystem {
+    type SystemData = (Read<'a, Time>, Read<'a, TimeScale>, Write<'a, Run>);
+
+    fn run(&mut self, (time, time_scale, mut run): Self::SystemData) {
+        if run.over {
+            return;
+        }
+        let delta = time_scale.delta(&time).min(run.remaining);
+        run.remaining -= delta;
+        run.elapsed += delta;
+        if run.remaining <= 0. {
+            event!(Level::INFO, "Run over with a score of {}", run.score);
+            run.over = true;
+        }
+    }
+}
+
+/// Fill the arena with what the game needs and the save doesn't hold: a player if there is none,
+/// stars up to the `GameplayConfig`, and the sprites of everyone.
+pub fn populate(world: &mut World) {
+    let config = world.read_resource::<GameplayConfig>().clone();
+    let (sheet, sprite_heights) = match gameplay_sheet(world, &config) {
+        Some(sheet) => sheet,
+        None => return,
+    };
+
+    let (players, pickups) = {
+        let players = world.read_storage::<Player>();
+        let pickups = world.read_storage::<Pickup>();
+        ((&players).join().count(), (&pickups).join().count())
+    };
+    if players == 0 {
+        let mut transform = Transform::default();
+        // drawn over the stars
+        transform.set_translation_z(0.1);
+        world
+            .create_entity()
+            .with(Saved)
+            .with(Player)
+            .with(transform)
+            .build();
+    }
+    for _ in pickups..config.star_count {
+        create_pickup(world, &config, sprite_heights.len());
+    }
+
+    // sprites are not saved, they are given back to the player and stars from the save
+    let sprite = |number: usize, size: f32| {
+        let number = number.min(sprite_heights.len() - 1);
+        (
+            SpriteRender {
+                sprite_sheet: sheet.clone(),
+                sprite_number: number,
+            },
+            size / sprite_heights[number],
+        )
+    };
+    world.exec(
+        |(entities, players, pickups, mut transforms, mut sprites, mut tints, mut targets): (
+            Entities<'_>,
+            ReadStorage<'_, Player>,
+            ReadStorage<'_, Pickup>,
+            WriteStorage<'_, Transform>,
+            WriteStorage<'_, SpriteRender>,
+            WriteStorage<'_, Tint>,
+            WriteStorage<'_, CameraTarget>,
+        )| {
+            for (entity, _) in (&entities, &players).join() {
+                let (render, scale) = sprite(config.player_sprite, config.player_size);
+                let _ = sprites.insert(entity, render);
+                let _ = targets.insert(entity, CameraTarget);
+                if let Some(transform) = transforms.get_mut(entity) {
+                    transform.set_scale(Vector3::new(scale, scale, 1.));
+                }
+            }
+            for (entity, pickup) in (&entities, &pickups).join() {
+                let (render, scale) = sprite(pickup.sprite, config.star_size);
+                let _ = sprites.insert(entity, render);
+                let _ = tints.insert(entity, Tint(Srgba::new(1., 0.85, 0.3, 1.)));
+                if let Some(transform) = transforms.get_mut(entity) {
+                    transform.set_scale(Vector3::new(scale, scale, 1.));
+                }
+            }
+        },
+    );
+}
+
+fn gameplay_sheet(
+    world: &World,
+    config: &GameplayConfig,
+) -> Option<(Handle<SpriteSheet>, Vec<f32>)> {
+    let sheet = world
+        .try_fetch::<Preloaded>()
+        .and_then(|preloaded| preloaded.sprite_sheet(world, &config.sheet))
+        .filter(|(_, sprite_heights)| !sprite_heights.is_empty());
+    if sheet.is_none() {
+        event!(
+            Level::ERROR,
+            "Gameplay sheet {} is not loaded",
+            config.sheet
+        );
+    }
+    sheet
+}
+
+fn create_pickup(world: &mut World, config: &GameplayConfig, sprite_count: usize) {
+    let mut transform = Transform::default();
+    let (x, y) = random_position(&config.arena, config.star_size);
+    transform.set_translation_xyz(x, y, 0.);
+    world
+        .create_entity()
+        .with(Saved)
+        .with(Pickup {
+            value: config.star_score,
+            sprite: rand::thread_rng().gen_range(0, sprite_count),
+        })
+        .with(transform)
+        .build();
+}
+
+/// Add `count` stars to the arena, over the number from the `GameplayConfig`.
+pub fn spawn_pickups(world: &mut World, count: usize) {
+    let config = world.read_resource::<GameplayConfig>().clone();
+    let sprite_count = match gameplay_sheet(world, &config) {
+        Some((_, sprite_heights)) => sprite_heights.len(),
+        None => return,
+    };
+    for _ in 0..count {
+        create_pickup(world, &config, sprite_count);
+    }
+    populate(world);
+}
+
+/// A random position in `arena`, at least `margin` away from its edges. Along the sides of the
+/// arena too narrow for it, positions are centered.
+pub fn random_position(arena: &Bounds, margin: f32) -> (f32, f32) {
+    let mut rng = rand::thread_rng();
+    let mut between = |min: f32, max: f32| {
+        if max - min > margin * 2. {
+            rng.gen_range(min + margin, max - margin)
+        } else {
+            (min + max) / 2.
+        }
+    };
+    (
+        between(arena.min.0, arena.max.0),
+        between(arena.min.1, arena.max.1),
+    )
+}
+
+/// Console commands to change the gameplay, removed by `unregister_commands`.
+pub fn register_commands(world: &World) {
+    let mut commands = world.write_resource::<Commands>();
+    commands.register(
+        "spawn",
+        "Add stars to collect",
+        vec![
+            Param::new("what", ArgKind::Choice(vec!["star".to_string()])),
+            Param::optional("count", ArgKind::Integer),
+        ],
+        |world, args| {
+            let count = args.integer(1).unwrap_or(1);
+            if count < 1 || count > MAX_SPAWN {
+                return Err(amethyst::Error::from_string(format!(
+                    "count should be between 1 and {}",
+                    MAX_SPAWN
+                )));
+            }
+            spawn_pickups(world, count as usize);
+            Ok(format!("Spawned {} stars", count))
+        },
+    );
+    commands.register(
+        "timescale",
+        "Change the speed of the gameplay, 1 is the normal speed",
+        vec![Param::new("scale", ArgKind::Number)],
+        |world, args| {
+            let scale = args.number(0).unwrap_or(1.);
+            if scale.is_nan() || scale <= 0. || scale > MAX_TIME_SCALE {
+                return Err(amethyst::Error::from_string(format!(
+                    "scale should be above 0 and at most {}",
+                    MAX_TIME_SCALE
+                )));
+            }
+            world.write_resource::<TimeScale>().scale = scale;
+            Ok(format!("Time scale: {}", scale))
+        },
+    );
+}
+
+pub fn unregister_commands(world: &World) {
+    let mut commands = world.write_resource::<Commands>();
+    for name in COMMANDS {
+        commands.unregister(name);
+    }
+}
+
+/// Systems that make up the gameplay. They are dispatched by the game state itself, so they
+/// stop whenever another state is displayed over it.
+pub struct GameplayDispatcher {
+    dispatcher: Dispatcher<'static, 'static>,
+}
+
+impl GameplayDispatcher {
+    pub fn new(world: &mut World) -> Self {
+        let mut dispatcher = DispatcherBuilder::new()
+            .with_pool(world.read_resource::<ArcThreadPool>().clone())
+            .with(PlaytimeSystem, "playtime", &[])
+            .with(PlayerSystem, "player", &[])
+            .with(PickupSystem, "pickup", &["player"])
+            .with(RunSystem, "run", &["pickup"])
+            .with(HudSystem, "hud", &["run"])
+            .build();
+        dispatcher.setup(world);
+        Self { dispatcher }
+    }
+
+    /// Run the gameplay systems, unless frame stepping holds them.
+    pub fn dispatch(&mut self, world: &World) {
+        if world.write_resource::<TimeScale>().advance() {
+            self.dispatcher.dispatch(world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_positions_keep_away_from_the_edges() {
+        let arena = Bounds {
+            min: (-100., 0.),
+            max: (100., 50.),
+        };
+        for _ in 0..100 {
+            let (x, y) = random_position(&arena, 10.);
+            assert!(x >= -90. && x <= 90.);
+            assert!(y >= 10. && y <= 40.);
+        }
+    }
+
+    #[test]
+    fn random_positions_are_centered_in_narrow_arenas() {
+        let arena = Bounds {
+            min: (-100., 0.),
+            max: (100., 16.),
+        };
+        let (x, y) = random_position(&arena, 32.);
+        assert!(x >= -68. && x <= 68.);
+        assert_eq!(y, 8.);
+        let (x, _) = random_position(
+            &Bounds {
+                min: (5., 0.),
+                max: (5., 100.),
+            },
+            1.,
+        );
+        assert_eq!(x, 5.);
+    }
+}
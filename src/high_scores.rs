@@ -0,0 +1,267 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{gameplay::Run, paths};
+
+/// Entries kept in the table.
+pub const HIGH_SCORE_COUNT: usize = 10;
+/// Longest name that can be entered.
+pub const MAX_NAME_LENGTH: usize = 12;
+const HIGH_SCORES_VERSION: u32 = 1;
+
+/// A run that made it to the table.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HighScore {
+    pub name: String,
+    /// Seconds since the Unix epoch.
+    pub date: u64,
+    pub score: u32,
+    pub collected: u32,
+    /// Length of the run, in seconds.
+    pub time: f32,
+}
+
+impl HighScore {
+    pub fn new(name: &str, run: &Run) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Self {
+            name: name.to_string(),
+            date,
+            score: run.score,
+            collected: run.collected,
+            time: run.elapsed,
+        }
+    }
+
+    pub fn date(&self) -> String {
+        Local
+            .timestamp(self.date as i64, 0)
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+}
+
+/// The table as written in `<user data>/high_scores.ron`. The checksum covers the entries, so
+/// that a file edited by hand is noticed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct HighScoresFile {
+    version: u32,
+    #[serde(default)]
+    last_name: String,
+    entries: Vec<HighScore>,
+    checksum: u64,
+}
+
+/// FNV-1a, stable across builds unlike the hashers of the standard library.
+fn checksum(entries: &[HighScore]) -> amethyst::Result<u64> {
+    let content = ron::ser::to_string(entries)?;
+    Ok(content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    }))
+}
+
+/// Read a table, checking that its entries were not edited.
+fn parse(content: &str) -> amethyst::Result<HighScoresFile> {
+    let file = ron::de::from_str::<HighScoresFile>(content)?;
+    if checksum(&file.entries)? == file.checksum {
+        Ok(file)
+    } else {
+        Err(amethyst::Error::from_string("checksum mismatch"))
+    }
+}
+
+fn high_scores_path() -> Option<PathBuf> {
+    paths::user_data_dir().map(|dir| dir.join("high_scores.ron"))
+}
+
+/// The best runs played on this computer, best first.
+#[derive(Debug, Clone, Default)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+    /// Name entered for the last record, offered again for the next one.
+    pub last_name: String,
+}
+
+impl HighScores {
+    /// The saved table, or an empty one if there is none. A table that fails its checksum is
+    /// set aside and replaced by an empty one.
+    pub fn load() -> Self {
+        let path = match high_scores_path().filter(|path| path.exists()) {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(amethyst::Error::from)
+            .and_then(|content| parse(&content));
+        match loaded {
+            Ok(file) => Self {
+                entries: file.entries,
+                last_name: file.last_name,
+            },
+            Err(err) => {
+                event!(
+                    Level::WARN,
+                    "Error loading high scores from {}, starting a new table: {}",
+                    path.display(),
+                    err
+                );
+                let corrupt = path.with_extension("ron.corrupt");
+                if let Err(err) = std::fs::rename(&path, &corrupt) {
+                    event!(Level::WARN, "Error moving {}: {}", path.display(), err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> amethyst::Result<()> {
+        let path = high_scores_path()
+            .ok_or_else(|| amethyst::Error::from_string("no user data directory"))?;
+        paths::write_atomic(&path, self.to_ron()?.as_bytes())?;
+        Ok(())
+    }
+
+    fn to_ron(&self) -> amethyst::Result<String> {
+        let file = HighScoresFile {
+            version: HIGH_SCORES_VERSION,
+            last_name: self.last_name.clone(),
+            entries: self.entries.clone(),
+            checksum: checksum(&self.entries)?,
+        };
+        Ok(ron::ser::to_string_pretty(&file, Default::default())?)
+    }
+
+    pub fn best(&self) -> Option<&HighScore> {
+        self.entries.first()
+    }
+
+    /// Would a run with this score enter the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < HIGH_SCORE_COUNT
+                || self.entries.last().map_or(true, |last| score > last.score))
+    }
+
+    /// Add `entry` to the table if it qualifies, returning its rank starting from 0. Ties go
+    /// to the older entry.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|existing| entry.score > existing.score)
+            .unwrap_or_else(|| self.entries.len());
+        self.last_name = entry.name.clone();
+        self.entries.insert(rank, entry);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            date: 0,
+            score,
+            collected: score / 10,
+            time: 60.,
+        }
+    }
+
+    fn names(high_scores: &HighScores) -> Vec<&str> {
+        high_scores
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn entries_are_ranked_best_first() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert(entry("b", 20)), Some(0));
+        assert_eq!(high_scores.insert(entry("a", 30)), Some(0));
+        assert_eq!(high_scores.insert(entry("c", 10)), Some(2));
+        assert_eq!(names(&high_scores), vec!["a", "b", "c"]);
+        assert_eq!(high_scores.best().map(|best| best.score), Some(30));
+        assert_eq!(high_scores.last_name, "c");
+    }
+
+    #[test]
+    fn ties_go_to_the_older_entry() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("first", 20));
+        assert_eq!(high_scores.insert(entry("second", 20)), Some(1));
+        high_scores.insert(entry("third", 20));
+        assert_eq!(names(&high_scores), vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn table_is_truncated_to_its_size() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=HIGH_SCORE_COUNT as u32 {
+            high_scores.insert(entry(&score.to_string(), score * 10));
+        }
+        assert_eq!(high_scores.entries.len(), HIGH_SCORE_COUNT);
+
+        // the lowest score is needed to enter a full table, a tie is not enough
+        assert!(!high_scores.qualifies(10));
+        assert_eq!(high_scores.insert(entry("tie", 10)), None);
+        assert!(high_scores.qualifies(11));
+        assert_eq!(
+            high_scores.insert(entry("new", 15)),
+            Some(HIGH_SCORE_COUNT - 1)
+        );
+        assert_eq!(high_scores.entries.len(), HIGH_SCORE_COUNT);
+        assert_eq!(high_scores.entries.last().map(|last| last.score), Some(15));
+    }
+
+    #[test]
+    fn empty_runs_do_not_qualify() {
+        assert!(!HighScores::default().qualifies(0));
+        assert!(HighScores::default().qualifies(1));
+    }
+
+    #[test]
+    fn saved_table_reads_back() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("a", 30));
+        high_scores.insert(entry("b", 20));
+        let file = parse(&high_scores.to_ron().unwrap()).expect("table is valid");
+        assert_eq!(file.entries, high_scores.entries);
+        assert_eq!(file.last_name, "b");
+    }
+
+    #[test]
+    fn edited_table_is_detected() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("a", 30));
+        let content = high_scores.to_ron().unwrap();
+        assert!(content.contains("score: 30"));
+        assert!(parse(&content.replace("score: 30", "score: 9999")).is_err());
+    }
+
+    #[test]
+    fn checksum_depends_on_entries_and_their_order() {
+        let entries = vec![entry("a", 30), entry("b", 20)];
+        let reversed = vec![entry("b", 20), entry("a", 30)];
+        assert_eq!(checksum(&entries).unwrap(), checksum(&entries).unwrap());
+        assert_ne!(checksum(&entries).unwrap(), checksum(&reversed).unwrap());
+        assert_ne!(checksum(&entries).unwrap(), checksum(&[]).unwrap());
+    }
+}
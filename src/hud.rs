@@ -0,0 +1,114 @@
+use amethyst::{
+    core::transform::Parent,
+    ecs::prelude::{
+        Component, DenseVecStorage, Entity, Join, Read, ReadStorage, System, World, WorldExt,
+        WriteStorage,
+    },
+    prelude::Builder,
+    ui::{Anchor, Stretch, UiText, UiTransform},
+};
+
+use tracing::instrument;
+
+use crate::{
+    gameplay::Run,
+    i18n::Translations,
+    ui_scheme::{load_font, COLOR_3},
+};
+
+const HUD_HEIGHT: f32 = 60.;
+const HUD_MARGIN: f32 = 20.;
+
+/// A text of the head-up display, kept up to date with the `Run` by the `HudSystem`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HudText {
+    Score,
+    TimeLeft,
+}
+
+impl Component for HudText {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Seconds as minutes and seconds, rounded up.
+pub fn format_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.).ceil() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Create the head-up display: the score at the top left of the window and the time left at
+/// the top right. Deleting the returned entity removes it.
+pub fn create(world: &mut World) -> Entity {
+    let font = load_font(world, "font/mandrill.ttf");
+    let root = world
+        .create_entity()
+        .with(
+            UiTransform::new(
+                "hud".to_string(),
+                Anchor::TopMiddle,
+                Anchor::TopMiddle,
+                0.,
+                0.,
+                1.,
+                20.,
+                HUD_HEIGHT,
+            )
+            .with_stretch(Stretch::X { x_margin: 0. }),
+        )
+        .build();
+
+    for (hud_text, id, anchor, x) in &[
+        (HudText::Score, "hud_score", Anchor::MiddleLeft, HUD_MARGIN),
+        (
+            HudText::TimeLeft,
+            "hud_time",
+            Anchor::MiddleRight,
+            -HUD_MARGIN,
+        ),
+    ] {
+        let mut text = UiText::new(font.clone(), String::new(), COLOR_3, 40.);
+        text.align = *anchor;
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                id.to_string(),
+                *anchor,
+                *anchor,
+                *x,
+                0.,
+                1.,
+                500.,
+                HUD_HEIGHT,
+            ))
+            .with(text)
+            .with(*hud_text)
+            .with(Parent { entity: root })
+            .build();
+    }
+    root
+}
+
+/// Displays the score and time left of the `Run` in the `HudText`s.
+#[derive(Debug, Default)]
+pub struct HudSystem;
+
+impl<'a> System<'a> for HudSystem {
+    type SystemData = (
+        Read<'a, Run>,
+        Read<'a, Translations>,
+        ReadStorage<'a, HudText>,
+        WriteStorage<'a, UiText>,
+    );
+
+    #[instrument(skip(run, translations, hud_texts, texts), level = "info")]
+    fn run(&mut self, (run, translations, hud_texts, mut texts): Self::SystemData) {
+        for (hud_text, text) in (&hud_texts, &mut texts).join() {
+            text.text = match hud_text {
+                HudText::Score => format!("{} {}", translations.tr("Score"), run.score),
+                HudText::TimeLeft => {
+                    format!("{} {}", translations.tr("Time"), format_time(run.remaining))
+                }
+            };
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use amethyst::{config::Config, utils::application_root_dir};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+/// Texts of the UI in the current language, from `assets/i18n/<language>.ron`. Texts are
+/// written in English in the code, and used as keys.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Translations {
+    pub texts: HashMap<String, String>,
+}
+
+impl Translations {
+    /// Load the translations for `language`, English needs none.
+    pub fn for_language(language: &str) -> Self {
+        if language == "en" {
+            return Self::default();
+        }
+        application_root_dir()
+            .map_err(amethyst::Error::from)
+            .and_then(|root| {
+                let path = root.join("assets/i18n").join(format!("{}.ron", language));
+                Ok(Translations::load(path)?)
+            })
+            .unwrap_or_else(|err| {
+                event!(
+                    Level::WARN,
+                    "Error loading {} translations: {}",
+                    language,
+                    err
+                );
+                Self::default()
+            })
+    }
+
+    /// The translation of `text`, or `text` itself if there is none.
+    pub fn tr<'a>(&'a self, text: &'a str) -> &'a str {
+        self.texts.get(text).map(String::as_str).unwrap_or(text)
+    }
+}
@@ -0,0 +1,239 @@
+use amethyst::{
+    core::transform::Parent,
+    ecs::prelude::Entity,
+    input::is_close_requested,
+    prelude::*,
+    ui::{Anchor, Stretch, UiText, UiTransform},
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console,
+    focus::focus,
+    high_scores::HighScores,
+    hud::format_time,
+    i18n::Translations,
+    router,
+    ui_scheme::{load_font, Button, COLOR_3, COLOR_TEXT_LIGHT},
+};
+
+const ROW_HEIGHT: f32 = 44.;
+// the columns of the table, with their center relative to the middle of the window
+const COLUMNS: &[(&str, f32, f32)] = &[
+    ("#", -440., 60.),
+    ("Name", -250., 300.),
+    ("Score", -20., 160.),
+    ("Stars", 130., 140.),
+    ("Time", 260., 120.),
+    ("Date", 420., 200.),
+];
+
+/// The high score table, best first.
+#[derive(Debug, Default)]
+pub struct HighScoresScreen {
+    ui_root: Option<Entity>,
+}
+
+impl HighScoresScreen {
+    fn create_text(
+        world: &mut World,
+        parent: Entity,
+        (id, x, y, width): (String, f32, f32, f32),
+        text: String,
+        color: [f32; 4],
+        font_size: f32,
+    ) {
+        let font = load_font(world, "font/mandrill.ttf");
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                id,
+                Anchor::TopMiddle,
+                Anchor::TopMiddle,
+                x,
+                y,
+                1.,
+                width,
+                ROW_HEIGHT,
+            ))
+            .with(UiText::new(font, text, color, font_size))
+            .with(Parent { entity: parent })
+            .build();
+    }
+}
+
+impl SimpleState for HighScoresScreen {
+    #[instrument(skip(data), level = "info")]
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let root = world
+            .create_entity()
+            .with(
+                UiTransform::new(
+                    "high_scores".to_string(),
+                    Anchor::Middle,
+                    Anchor::Middle,
+                    0.,
+                    0.,
+                    0.,
+                    20.,
+                    20.,
+                )
+                .with_stretch(Stretch::XY {
+                    x_margin: 0.,
+                    y_margin: 0.,
+                    keep_aspect_ratio: false,
+                }),
+            )
+            .build();
+
+        let (title, headers, rows) = {
+            let translations = world.read_resource::<Translations>();
+            let high_scores = world.read_resource::<HighScores>();
+            let headers = COLUMNS
+                .iter()
+                .map(|(header, _, _)| translations.tr(header).to_string())
+                .collect::<Vec<_>>();
+            let rows = high_scores
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(rank, entry)| {
+                    vec![
+                        (rank + 1).to_string(),
+                        entry.name.clone(),
+                        entry.score.to_string(),
+                        entry.collected.to_string(),
+                        format_time(entry.time),
+                        entry.date(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            (translations.tr("High Scores").to_string(), headers, rows)
+        };
+
+        Self::create_text(
+            world,
+            root,
+            ("high_scores_title".to_string(), 0., -20., 1000.),
+            title,
+            COLOR_3,
+            60.,
+        );
+        let mut y = -100.;
+        for (column, header) in COLUMNS.iter().zip(headers) {
+            Self::create_text(
+                world,
+                root,
+                (
+                    format!("high_scores_header_{}", column.0),
+                    column.1,
+                    y,
+                    column.2,
+                ),
+                header,
+                COLOR_3,
+                30.,
+            );
+        }
+        if rows.is_empty() {
+            y -= ROW_HEIGHT;
+            let text = world
+                .read_resource::<Translations>()
+                .tr("No high scores yet")
+                .to_string();
+            Self::create_text(
+                world,
+                root,
+                ("high_scores_empty".to_string(), 0., y, 1000.),
+                text,
+                COLOR_TEXT_LIGHT,
+                30.,
+            );
+        }
+        for (rank, row) in rows.into_iter().enumerate() {
+            y -= ROW_HEIGHT;
+            for (column, text) in COLUMNS.iter().zip(row) {
+                Self::create_text(
+                    world,
+                    root,
+                    (
+                        format!("high_scores_{}_{}", rank, column.0),
+                        column.1,
+                        y,
+                        column.2,
+                    ),
+                    text,
+                    COLOR_TEXT_LIGHT,
+                    30.,
+                );
+            }
+        }
+
+        let back = Button {
+            width: 400.,
+            height: 80.,
+            font_size: 40.,
+            action: Some(UiAction::Back),
+            ..Button::new("Back", "high_scores_back")
+        }
+        .create(
+            world,
+            root,
+            UiTransform::new(
+                "high_scores_back_container".to_string(),
+                Anchor::BottomMiddle,
+                Anchor::BottomMiddle,
+                0.,
+                60.,
+                0.,
+                0.,
+                0.,
+            ),
+        );
+        focus(world, back);
+
+        self.ui_root = Some(root);
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) {
+                    event!(Level::INFO, "Quitting Application!");
+                    Trans::Quit
+                } else {
+                    Trans::None
+                }
+            }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::BACK) => {
+                router::go(data.world, router::BACK)
+            }
+            StateEvent::Ui(_) => match ui_action(data.world, &event) {
+                Some(UiAction::Back) => router::go(data.world, router::BACK),
+                _ => Trans::None,
+            },
+            _ => Trans::None,
+        }
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        if let Some(root_entity) = self.ui_root {
+            data.world
+                .delete_entity(root_entity)
+                .expect("Failed to remove HighScoresScreen");
+        }
+
+        self.ui_root = None;
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    assets::{AssetStorage, Completion, Handle, Loader, ProgressCounter},
+    audio::{OggFormat, Source, SourceHandle},
+    config::Config,
+    ecs::prelude::Entity,
+    input::is_close_requested,
+    prelude::*,
+    renderer::{ImageFormat, SpriteSheet, SpriteSheetFormat, Texture},
+    ui::{Anchor, FontAsset, LineMode, TtfFormat, UiLoader, UiText, UiTransform},
+    utils::application_root_dir,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console, router,
+    ui_scheme::{load_font, Menu, MenuButton, ProgressBar, ProgressBarFill, COLOR_3},
+    widget::{GameUiPrefab, GameWidget},
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpriteSheetAsset {
+    pub texture: String,
+    pub sheet: String,
+}
+
+/// Assets to load before reaching the main menu, from `assets/manifest.ron`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub fonts: Vec<String>,
+    pub textures: Vec<String>,
+    pub sounds: Vec<String>,
+    pub sprite_sheets: Vec<SpriteSheetAsset>,
+    /// UI prefabs.
+    pub ui: Vec<String>,
+}
+
+/// Handles to the assets loaded by the loading screen, by path. Keeping them here keeps the
+/// assets alive.
+#[derive(Debug, Default)]
+pub struct Preloaded {
+    pub fonts: HashMap<String, Handle<FontAsset>>,
+    pub textures: HashMap<String, Handle<Texture>>,
+    pub sounds: HashMap<String, SourceHandle>,
+    /// Sprite sheets, by path of the sheet definition.
+    pub sprite_sheets: HashMap<String, Handle<SpriteSheet>>,
+    pub ui: HashMap<String, Handle<GameUiPrefab>>,
+}
+
+impl Preloaded {
+    /// A loaded sprite sheet, with the height of each of its sprites.
+    pub fn sprite_sheet(
+        &self,
+        world: &World,
+        path: &str,
+    ) -> Option<(Handle<SpriteSheet>, Vec<f32>)> {
+        let handle = self.sprite_sheets.get(path)?;
+        let heights = world
+            .read_resource::<AssetStorage<SpriteSheet>>()
+            .get(handle)?
+            .sprites
+            .iter()
+            .map(|sprite| sprite.height)
+            .collect::<Vec<_>>();
+        Some((handle.clone(), heights))
+    }
+
+    fn load(world: &World, manifest: &Manifest, progress: &mut ProgressCounter) -> Self {
+        let loader = world.read_resource::<Loader>();
+        let fonts = world.read_resource::<AssetStorage<FontAsset>>();
+        let textures = world.read_resource::<AssetStorage<Texture>>();
+        let sounds = world.read_resource::<AssetStorage<Source>>();
+        let sprite_sheets = world.read_resource::<AssetStorage<SpriteSheet>>();
+
+        let mut preloaded = Preloaded::default();
+        for name in &manifest.fonts {
+            let handle = loader.load(name.as_str(), TtfFormat, &mut *progress, &fonts);
+            preloaded.fonts.insert(name.clone(), handle);
+        }
+        for name in &manifest.textures {
+            let handle = loader.load(
+                name.as_str(),
+                ImageFormat::default(),
+                &mut *progress,
+                &textures,
+            );
+            preloaded.textures.insert(name.clone(), handle);
+        }
+        for name in &manifest.sounds {
+            let handle = loader.load(name.as_str(), OggFormat, &mut *progress, &sounds);
+            preloaded.sounds.insert(name.clone(), handle);
+        }
+        for asset in &manifest.sprite_sheets {
+            let texture = loader.load(
+                asset.texture.as_str(),
+                ImageFormat::default(),
+                &mut *progress,
+                &textures,
+            );
+            preloaded
+                .textures
+                .insert(asset.texture.clone(), texture.clone());
+            let sheet = loader.load(
+                asset.sheet.as_str(),
+                SpriteSheetFormat(texture),
+                &mut *progress,
+                &sprite_sheets,
+            );
+            preloaded.sprite_sheets.insert(asset.sheet.clone(), sheet);
+        }
+        world.exec(|ui_loader: UiLoader<'_, GameWidget>| {
+            for name in &manifest.ui {
+                let handle = ui_loader.load(name.as_str(), &mut *progress);
+                preloaded.ui.insert(name.clone(), handle);
+            }
+        });
+        preloaded
+    }
+}
+
+/// Loads every asset of the manifest while displaying a progress bar. Lists the assets that
+/// failed to load if any.
+#[derive(Default)]
+pub struct LoadingScreen {
+    ui_root: Option<Entity>,
+    progress: ProgressCounter,
+    bar: Option<ProgressBarFill>,
+    label: Option<Entity>,
+    failed: bool,
+}
+
+impl LoadingScreen {
+    fn show_errors(&mut self, world: &mut World) {
+        if let Some(root) = self.ui_root.take() {
+            world
+                .delete_entity(root)
+                .expect("Failed to remove LoadingScreen");
+        }
+        self.bar = None;
+        self.label = None;
+
+        let failed_assets = self
+            .progress
+            .errors()
+            .iter()
+            .map(|error| {
+                event!(
+                    Level::ERROR,
+                    "Error loading {}: {}",
+                    error.asset_name,
+                    error.error
+                );
+                error.asset_name.clone()
+            })
+            .collect::<Vec<_>>();
+
+        let menu = Menu {
+            button_size: (600., 80.),
+            ..Menu::new(
+                "loading_error",
+                vec![
+                    MenuButton::new("Continue", "loading_continue", UiAction::ContinueLoading),
+                    MenuButton::new("Quit", "loading_quit", UiAction::Quit),
+                ],
+            )
+        };
+        let root = menu.create(world);
+
+        let font = load_font(world, "font/mandrill.ttf");
+        let mut text = UiText::new(
+            font,
+            format!(
+                "Some assets could not be loaded:\n{}",
+                failed_assets.join("\n")
+            ),
+            COLOR_3,
+            30.,
+        );
+        text.line_mode = LineMode::Wrap;
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                "loading_errors".to_string(),
+                Anchor::TopMiddle,
+                Anchor::TopMiddle,
+                0.,
+                -20.,
+                1.,
+                1000.,
+                250.,
+            ))
+            .with(text)
+            .with(amethyst::core::transform::Parent { entity: root })
+            .build();
+
+        self.ui_root = Some(root);
+    }
+}
+
+impl SimpleState for LoadingScreen {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let manifest = application_root_dir()
+            .map_err(amethyst::Error::from)
+            .and_then(|root| Ok(Manifest::load(root.join("assets/manifest.ron"))?))
+            .unwrap_or_else(|err| {
+                event!(Level::ERROR, "Error loading asset manifest: {}", err);
+                Manifest::default()
+            });
+        let preloaded = Preloaded::load(world, &manifest, &mut self.progress);
+        world.insert(preloaded);
+
+        let root = world
+            .create_entity()
+            .with(UiTransform::new(
+                "loading".to_string(),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.,
+                0.,
+                0.,
+                0.,
+                0.,
+            ))
+            .build();
+        self.bar = Some(ProgressBar::new("loading_bar").create(
+            world,
+            root,
+            UiTransform::new(
+                "loading_bar_container".to_string(),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.,
+                0.,
+                0.,
+                0.,
+                0.,
+            ),
+        ));
+        let font = load_font(world, "font/mandrill.ttf");
+        self.label = Some(
+            world
+                .create_entity()
+                .with(UiTransform::new(
+                    "loading_label".to_string(),
+                    Anchor::Middle,
+                    Anchor::Middle,
+                    0.,
+                    -70.,
+                    0.,
+                    800.,
+                    50.,
+                ))
+                .with(UiText::new(font, "Loading".to_string(), COLOR_3, 40.))
+                .with(amethyst::core::transform::Parent { entity: root })
+                .build(),
+        );
+        self.ui_root = Some(root);
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if self.failed {
+            return Trans::None;
+        }
+        let world = &mut *data.world;
+
+        let total = self.progress.num_assets().max(1);
+        let done = self.progress.num_finished() + self.progress.num_failed();
+        if let Some(bar) = self.bar {
+            bar.set(world, done as f32 / total as f32);
+        }
+        if let Some(label) = self.label {
+            if let Some(text) = world.write_storage::<UiText>().get_mut(label) {
+                text.text = format!("Loading {}/{}", done, self.progress.num_assets());
+            }
+        }
+
+        match self.progress.complete() {
+            Completion::Complete => router::go(world, "done"),
+            Completion::Failed if done == self.progress.num_assets() => {
+                self.failed = true;
+                self.show_errors(world);
+                Trans::None
+            }
+            _ => Trans::None,
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) {
+                    event!(Level::INFO, "Quitting Application!");
+                    Trans::Quit
+                } else {
+                    Trans::None
+                }
+            }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::BACK) => {
+                router::go(data.world, router::BACK)
+            }
+            StateEvent::Ui(_) => match ui_action(data.world, &event) {
+                Some(UiAction::ContinueLoading) => router::go(data.world, "done"),
+                Some(UiAction::Quit) => Trans::Quit,
+                _ => Trans::None,
+            },
+            _ => Trans::None,
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        if let Some(root) = self.ui_root.take() {
+            data.world
+                .delete_entity(root)
+                .expect("Failed to remove LoadingScreen");
+        }
+        self.bar = None;
+        self.label = None;
+    }
+}
@@ -1,5 +1,6 @@
 use amethyst::{
     audio::AudioBundle,
+    config::Config,
     core::transform::TransformBundle,
     input::{InputBundle, StringBindings},
     prelude::*,
@@ -10,61 +11,224 @@ use amethyst::{
     },
     ui::{RenderUi, UiBundle},
     utils::{application_root_dir, fps_counter::FpsCounterBundle},
+    window::DisplayConfig,
 };
 
 use tracing::{event, Level};
 use tracing_subscriber;
 
+// background of the window, and of the captured frames
+const CLEAR_COLOR: [f32; 4] = [0.005, 0.005, 0.005, 1.0];
+
 mod about;
+mod action;
+mod audio;
+mod bindings;
+mod build_info;
+mod camera;
+mod capture;
+mod console;
+mod controls;
+mod debug_overlay;
 mod events;
+mod focus;
 mod game;
+mod game_over;
+mod gamepad;
+mod gameplay;
+mod high_scores;
+mod hud;
+mod i18n;
+mod leaderboard;
+mod link;
+mod loading;
 mod menu;
+mod options;
+mod paths;
 mod pause;
+mod pickup;
+mod player;
+mod router;
+mod save;
+mod screenshot;
+mod settings;
+mod slots;
 mod splash;
+mod starfield;
+mod toast;
 mod ui_scheme;
+mod widget;
+mod window_focus;
 
 pub fn main() -> amethyst::Result<()> {
-    let _subscriber = tracing_subscriber::fmt()
+    // events are also printed in the console, where their filter can be changed
+    let log_buffer = crate::console::LogBuffer::default();
+    let subscriber = tracing_subscriber::fmt()
         .with_max_level(tracing::Level::DEBUG)
         .with_env_filter("info,corylus=debug,gfx_backend_metal=error")
-        .init();
+        .with_writer(log_buffer.clone())
+        .with_filter_reloading();
+    let reload_handle = subscriber.reload_handle();
+    let log_filter = crate::console::LogFilter::new(move |filter| reload_handle.reload(filter));
+    let _subscriber = subscriber.init();
 
     amethyst::start_logger(Default::default());
 
+    let build_info = crate::build_info::BuildInfo::current();
+    event!(Level::INFO, "Corylus {}", build_info);
+    // panics are reported with the build they happened in
+    let default_hook = std::panic::take_hook();
+    let panic_build_info = build_info.clone();
+    std::panic::set_hook(Box::new(move |info| {
+        event!(
+            Level::ERROR,
+            "Corylus {} panicked: {}",
+            panic_build_info,
+            info
+        );
+        default_hook(info);
+    }));
+
     let app_root = application_root_dir()?;
 
     let display_config_path = app_root.join("config/display.ron");
     let assets_dir = app_root.join("assets");
 
+    // the player settings are needed before building the window, the config files only provide
+    // defaults for them
+    let mut display_config = DisplayConfig::load(&display_config_path)?;
+    let settings = crate::settings::UserSettings::load_or(crate::settings::UserSettings {
+        display: crate::settings::DisplaySettings::from_display_config(&display_config),
+        audio: crate::audio::AudioConfig::load(app_root.join("config/audio.ron")).unwrap_or_else(
+            |err| {
+                event!(Level::WARN, "Error loading audio config: {}", err);
+                Default::default()
+            },
+        ),
+        ..Default::default()
+    });
+    display_config.dimensions = Some(settings.display.resolution);
+    let bindings = crate::bindings::load(&app_root)?;
+    let theme = crate::ui_scheme::Theme::load(assets_dir.join("themes/default.ron"))
+        .unwrap_or_else(|err| {
+            event!(Level::WARN, "Error loading theme: {}", err);
+            Default::default()
+        });
+    let link_policy = crate::link::LinkPolicy::load(app_root.join("config/links.ron"))
+        .unwrap_or_else(|err| {
+            event!(
+                Level::WARN,
+                "Error loading link policy, links are disabled: {}",
+                err
+            );
+            Default::default()
+        });
+    let starfield = crate::starfield::StarfieldConfig::load(app_root.join("config/starfield.ron"))
+        .unwrap_or_else(|err| {
+            event!(Level::WARN, "Error loading starfield: {}", err);
+            Default::default()
+        });
+    let gameplay = crate::gameplay::GameplayConfig::load(app_root.join("config/gameplay.ron"))
+        .unwrap_or_else(|err| {
+            event!(Level::WARN, "Error loading gameplay: {}", err);
+            Default::default()
+        });
+    let camera = crate::camera::CameraConfig::load(app_root.join("config/camera.ron"))
+        .unwrap_or_else(|err| {
+            event!(Level::WARN, "Error loading camera: {}", err);
+            Default::default()
+        });
+
+    // the frames rendered to the window, read back for the screenshots
+    let frames = crate::capture::WindowFrames::default();
+    let mut screenshots = crate::screenshot::Screenshots::default();
+    screenshots.set_source(frames.clone());
+
     let game_data = GameDataBuilder::default()
-        .with_bundle(TransformBundle::new())?
-        .with_bundle(InputBundle::<StringBindings>::new())?
-        .with_bundle(UiBundle::<StringBindings>::new())?
+        // placed before the transforms are updated, so they show where the camera is this frame
+        .with(crate::camera::CameraSystem, "camera", &[])
+        .with(crate::starfield::StarfieldSystem, "starfield", &["camera"])
+        .with_bundle(TransformBundle::new().with_dep(&["camera", "starfield"]))?
+        .with_bundle(InputBundle::<StringBindings>::new().with_bindings(bindings))?
+        .with_bundle(UiBundle::<StringBindings, crate::widget::GameWidget>::new())?
         .with_bundle(AudioBundle::default())?
+        .with_system_desc(
+            crate::window_focus::WindowFocusSystemDesc::default(),
+            "window_focus",
+            &[],
+        )
+        .with_system_desc(
+            crate::gamepad::GamepadSystemDesc::default(),
+            "gamepad",
+            &["input_system"],
+        )
+        .with_system_desc(
+            crate::focus::UiFocusSystemDesc::default(),
+            "ui_focus",
+            &["input_system", "gamepad"],
+        )
         .with_system_desc(
             crate::events::UiEventHandlerSystemDesc::default(),
             "ui_event_handler",
-            &[],
+            &["ui_focus"],
         )
-        // Necessary for the FPS counter in the upper left corner to work.
-        // (simply uncommenting will fail at runtime, since the resource is expected to exist, you
-        // need to uncomment line 107-114 in game.rs for it to still work)
+        .with_system_desc(
+            crate::audio::UiSoundSystemDesc::default(),
+            "ui_sound",
+            &["ui_focus", "window_focus"],
+        )
+        .with_system_desc(
+            crate::link::LinkSystemDesc::default(),
+            "link",
+            &["ui_focus"],
+        )
+        .with_system_desc(
+            crate::screenshot::ScreenshotSystemDesc::default(),
+            "screenshot",
+            &["input_system"],
+        )
+        .with(
+            crate::toast::ToastSystem::default(),
+            "toast",
+            &["link", "screenshot"],
+        )
+        .with_system_desc(
+            crate::console::ConsoleSystemDesc::default(),
+            "console",
+            &["input_system"],
+        )
+        // Samples the frame rate in the `FpsCounter` resource, shown by the debug overlay.
         .with_bundle(FpsCounterBundle)?
+        .with_system_desc(
+            crate::debug_overlay::DebugOverlaySystemDesc::default(),
+            "debug_overlay",
+            &["input_system"],
+        )
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
-                .with_plugin(
-                    RenderToWindow::from_config_path(display_config_path)?
-                        .with_clear([0.005, 0.005, 0.005, 1.0]),
-                )
+                .with_plugin(RenderToWindow::from_config(display_config).with_clear(CLEAR_COLOR))
                 .with_plugin(RenderUi::default())
-                .with_plugin(RenderFlat2D::default()),
+                .with_plugin(RenderFlat2D::default())
+                .with_plugin(crate::capture::RenderCapture::new(frames).with_clear(CLEAR_COLOR)),
         )?;
 
-    let mut game = Application::new(
-        assets_dir,
-        crate::splash::SplashScreen::default(),
-        game_data,
-    )?;
+    let router = crate::router::Router::from_config()?;
+
+    let mut game = Application::build(assets_dir, crate::router::Boot)?
+        .with_resource(router)
+        .with_resource(build_info)
+        .with_resource(settings)
+        .with_resource(theme)
+        .with_resource(link_policy)
+        .with_resource(starfield)
+        .with_resource(camera)
+        .with_resource(gameplay)
+        .with_resource(crate::high_scores::HighScores::load())
+        .with_resource(log_buffer)
+        .with_resource(log_filter)
+        .with_resource(crate::console::Commands::builtin())
+        .with_resource(screenshots)
+        .build(game_data)?;
     event!(Level::INFO, "Starting...");
     game.run();
 
@@ -1,23 +1,16 @@
-use amethyst::{
-    ecs::prelude::Entity,
-    input::{is_close_requested, is_key_down},
-    prelude::*,
-    ui::{Anchor, UiEvent, UiEventType, UiFinder},
-    winit::VirtualKeyCode,
-};
+use amethyst::{ecs::prelude::Entity, input::is_close_requested, prelude::*};
 
 use tracing::{event, instrument, Level};
 
-use crate::{about::AboutScreen, game::Game};
-
-const BUTTON_START: &str = "start";
-const BUTTON_ABOUT: &str = "about";
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console, router,
+    ui_scheme::{Menu, MenuButton},
+};
 
 #[derive(Default, Debug)]
 pub struct MainMenu {
     ui_root: Option<Entity>,
-    button_start: Option<Entity>,
-    button_about: Option<Entity>,
 }
 
 impl SimpleState for MainMenu {
@@ -25,86 +18,52 @@ impl SimpleState for MainMenu {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         let world = data.world;
 
-        let menu = world
-            .create_entity()
-            .with(amethyst::ui::UiTransform::new(
-                format!("menu"),
-                Anchor::Middle,
-                Anchor::Middle,
-                0.,
-                0.,
-                0.,
-                0.,
-                0.,
-            ))
-            .build();
-
-        let buttons = vec![("Start Game", BUTTON_START), ("About", BUTTON_ABOUT)];
-        buttons.iter().enumerate().for_each(|(i, (text, id))| {
-            crate::ui_scheme::Button::new(text, id).create(
-                world,
-                menu,
-                amethyst::ui::UiTransform::new(
-                    format!("{}_container", id),
-                    Anchor::Middle,
-                    Anchor::Middle,
-                    0.,
-                    ((buttons.len() as f32) / 2. - 0.5) * 150. - (i as f32) * (150. + 10.),
-                    0.,
-                    0.,
-                    0.,
-                ),
-            );
-        });
+        let menu = Menu {
+            button_size: (800., 100.),
+            ..Menu::new(
+                "menu",
+                vec![
+                    MenuButton::new("Start Game", "start", UiAction::StartGame),
+                    MenuButton::new("Load Game", "load", UiAction::OpenLoad),
+                    MenuButton::new("High Scores", "high_scores", UiAction::OpenHighScores),
+                    MenuButton::new("Options", "options", UiAction::OpenOptions),
+                    MenuButton::new("Controls", "controls", UiAction::OpenControls),
+                    MenuButton::new("About", "about", UiAction::OpenAbout),
+                ],
+            )
+        };
 
-        self.ui_root = Some(menu);
+        self.ui_root = Some(menu.create(world));
     }
 
-    #[instrument(skip(state_data), level = "info")]
-    fn update(&mut self, state_data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        // only search for buttons if they have not been found yet
-        let StateData { world, .. } = state_data;
-
-        if self.button_start.is_none() || self.button_about.is_none() {
-            world.exec(|ui_finder: UiFinder<'_>| {
-                self.button_start = ui_finder.find(&format!("{}", BUTTON_START));
-                self.button_about = ui_finder.find(&format!("{}", BUTTON_ABOUT));
-            });
-        }
-
-        Trans::None
-    }
-
-    #[instrument(skip(_data), level = "info")]
+    #[instrument(skip(data), level = "info")]
     fn handle_event(
         &mut self,
-        _data: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
-        match event {
+        match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
             StateEvent::Window(event) => {
-                if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                if is_close_requested(&event) {
                     event!(Level::INFO, "Quitting Application!");
                     Trans::Quit
                 } else {
                     Trans::None
                 }
             }
-            StateEvent::Ui(UiEvent {
-                event_type: UiEventType::Click,
-                target,
-            }) => {
-                if Some(target) == self.button_about {
-                    event!(Level::INFO, "Switching to AboutScreen!");
-                    return Trans::Switch(Box::new(AboutScreen::default()));
-                }
-                if Some(target) == self.button_start {
-                    event!(Level::INFO, "Switching to Game!");
-                    return Trans::Switch(Box::new(Game::default()));
-                }
-
-                Trans::None
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::BACK) => {
+                router::go(data.world, router::BACK)
             }
+            StateEvent::Ui(_) => match ui_action(data.world, &event) {
+                Some(UiAction::OpenAbout) => router::go(data.world, "about"),
+                Some(UiAction::OpenHighScores) => router::go(data.world, "high_scores"),
+                Some(UiAction::OpenControls) => router::go(data.world, "controls"),
+                Some(UiAction::OpenOptions) => router::go(data.world, "options"),
+                Some(UiAction::OpenLoad) => router::go(data.world, "load"),
+                Some(UiAction::StartGame) => router::go(data.world, "start_game"),
+                _ => Trans::None,
+            },
             _ => Trans::None,
         }
     }
@@ -119,7 +78,5 @@ impl SimpleState for MainMenu {
         }
 
         self.ui_root = None;
-        self.button_start = None;
-        self.button_about = None;
     }
 }
@@ -0,0 +1,237 @@
+use amethyst::{input::is_close_requested, prelude::*};
+
+use tracing::{event, instrument, Level};
+
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console,
+    i18n::Translations,
+    router,
+    settings::{cycle, UserSettings, BACKGROUND_FRAME_RATES, LANGUAGES, RESOLUTIONS, UI_SCALES},
+    ui_scheme::{Menu, MenuButton, MenuScreen},
+};
+
+const RESOLUTION: &str = "resolution";
+const FULLSCREEN: &str = "fullscreen";
+const LIMIT_FPS: &str = "limit_fps";
+const MASTER_VOLUME: &str = "master_volume";
+const SFX_VOLUME: &str = "sfx_volume";
+const PAUSE_UNFOCUSED: &str = "pause_unfocused";
+const MUTE_UNFOCUSED: &str = "mute_unfocused";
+const BACKGROUND_FRAME_RATE: &str = "background_frame_rate";
+const UI_SCALE: &str = "ui_scale";
+const LANGUAGE: &str = "language";
+
+/// Options in the order they are displayed, with their label.
+const OPTIONS: &[(&str, &str)] = &[
+    (RESOLUTION, "Resolution"),
+    (FULLSCREEN, "Fullscreen"),
+    (LIMIT_FPS, "Limit to 60 FPS"),
+    (MASTER_VOLUME, "Master volume"),
+    (SFX_VOLUME, "Effects volume"),
+    (PAUSE_UNFOCUSED, "Pause when unfocused"),
+    (MUTE_UNFOCUSED, "Mute when unfocused"),
+    (BACKGROUND_FRAME_RATE, "Unfocused frame rate"),
+    (UI_SCALE, "UI scale"),
+    (LANGUAGE, "Language"),
+];
+
+/// Change the option `name` of `settings` to its next value.
+fn cycle_option(settings: &mut UserSettings, name: &str) {
+    match name {
+        RESOLUTION => {
+            settings.display.resolution = cycle(RESOLUTIONS, &settings.display.resolution)
+        }
+        FULLSCREEN => settings.display.fullscreen = !settings.display.fullscreen,
+        LIMIT_FPS => settings.display.limit_fps = !settings.display.limit_fps,
+        MASTER_VOLUME => settings.audio.master_volume = next_volume(settings.audio.master_volume),
+        SFX_VOLUME => settings.audio.sfx_volume = next_volume(settings.audio.sfx_volume),
+        PAUSE_UNFOCUSED => settings.unfocused.pause = !settings.unfocused.pause,
+        MUTE_UNFOCUSED => settings.unfocused.mute = !settings.unfocused.mute,
+        BACKGROUND_FRAME_RATE => {
+            // unlimited comes after the last frame rate
+            let frame_rates = BACKGROUND_FRAME_RATES
+                .iter()
+                .map(|fps| Some(*fps))
+                .chain(std::iter::once(None))
+                .collect::<Vec<_>>();
+            settings.unfocused.frame_rate = cycle(&frame_rates, &settings.unfocused.frame_rate);
+        }
+        UI_SCALE => settings.ui_scale = cycle(UI_SCALES, &settings.ui_scale),
+        LANGUAGE => {
+            let languages = LANGUAGES
+                .iter()
+                .map(|(code, _)| code.to_string())
+                .collect::<Vec<_>>();
+            settings.language = cycle(&languages, &settings.language);
+        }
+        _ => event!(Level::WARN, "Unknown option {}", name),
+    }
+}
+
+/// Volumes go up by a tenth, then back to silent.
+fn next_volume(volume: f32) -> f32 {
+    if volume >= 0.95 {
+        0.
+    } else {
+        ((volume * 10.).round() + 1.) / 10.
+    }
+}
+
+/// The value of the option `name`, as displayed.
+fn option_value(settings: &UserSettings, translations: &Translations, name: &str) -> String {
+    let on_off = |value: bool| {
+        translations
+            .tr(if value { "On" } else { "Off" })
+            .to_string()
+    };
+    let percent = |value: f32| format!("{}%", (value * 100.).round());
+    match name {
+        RESOLUTION => format!(
+            "{}x{}",
+            settings.display.resolution.0, settings.display.resolution.1
+        ),
+        FULLSCREEN => on_off(settings.display.fullscreen),
+        LIMIT_FPS => on_off(settings.display.limit_fps),
+        MASTER_VOLUME => percent(settings.audio.master_volume),
+        SFX_VOLUME => percent(settings.audio.sfx_volume),
+        PAUSE_UNFOCUSED => on_off(settings.unfocused.pause),
+        MUTE_UNFOCUSED => on_off(settings.unfocused.mute),
+        BACKGROUND_FRAME_RATE => match settings.unfocused.frame_rate {
+            Some(fps) => format!("{} fps", fps),
+            None => translations.tr("Unlimited").to_string(),
+        },
+        UI_SCALE => percent(settings.ui_scale),
+        LANGUAGE => LANGUAGES
+            .iter()
+            .find(|(code, _)| *code == settings.language)
+            .map(|(_, language)| language.to_string())
+            .unwrap_or_else(|| settings.language.clone()),
+        _ => String::new(),
+    }
+}
+
+/// Lists the `UserSettings`, activating one changes it to its next value. Changes are applied right
+/// away so that they can be previewed, and reverted when leaving unless they were applied, which
+/// saves them in the player config.
+#[derive(Default, Debug)]
+pub struct OptionsScreen {
+    screen: MenuScreen,
+    // settings to go back to when leaving
+    applied: Option<UserSettings>,
+}
+
+impl OptionsScreen {
+    /// Rebuild the screen to display the current settings, then focus `focused`.
+    fn refresh(&mut self, world: &mut World, notice: &str, focused: UiAction) {
+        let mut buttons = {
+            let settings = world.read_resource::<UserSettings>();
+            let translations = world.read_resource::<Translations>();
+            OPTIONS
+                .iter()
+                .map(|(name, label)| {
+                    MenuButton::new(
+                        &format!(
+                            "{}: {}",
+                            translations.tr(label),
+                            option_value(&settings, &translations, name)
+                        ),
+                        &format!("option_{}", name),
+                        UiAction::CycleOption(name.to_string()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        buttons.push(MenuButton::new(
+            "Apply",
+            "options_apply",
+            UiAction::ApplyOptions,
+        ));
+        buttons.push(MenuButton::new("Cancel", "options_cancel", UiAction::Back));
+
+        let menu = Menu {
+            button_size: (800., 42.),
+            ..Menu::new("options", buttons)
+        };
+        self.screen.show(world, menu, notice, &focused);
+    }
+
+    fn change(&mut self, world: &mut World, name: String) {
+        let previous = world.read_resource::<UserSettings>().clone();
+        let mut settings = previous.clone();
+        cycle_option(&mut settings, &name);
+        event!(Level::INFO, "Previewing {} {:?}", name, settings);
+        settings.apply(world, Some(&previous));
+        self.refresh(world, "", UiAction::CycleOption(name));
+    }
+
+    /// Go back to the last applied settings.
+    fn revert(&mut self, world: &mut World) {
+        if let Some(applied) = self.applied.take() {
+            let current = world.read_resource::<UserSettings>().clone();
+            if current != applied {
+                event!(Level::INFO, "Reverting settings");
+                applied.apply(world, Some(&current));
+            }
+        }
+    }
+}
+
+impl SimpleState for OptionsScreen {
+    #[instrument(skip(data), level = "info")]
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        self.applied = Some(world.read_resource::<UserSettings>().clone());
+        self.refresh(world, "", UiAction::CycleOption(OPTIONS[0].0.to_string()));
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        let world = data.world;
+
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                event!(Level::INFO, "Quitting Application!");
+                return Trans::Quit;
+            }
+        }
+        if console::is_typing(world, &event) {
+            return Trans::None;
+        }
+        if bindings::is_action(&event, bindings::BACK) {
+            return router::go(world, router::BACK);
+        }
+
+        match ui_action(world, &event) {
+            Some(UiAction::CycleOption(name)) => {
+                self.change(world, name);
+                Trans::None
+            }
+            Some(UiAction::ApplyOptions) => {
+                let settings = world.read_resource::<UserSettings>().clone();
+                let notice = match settings.save() {
+                    Ok(()) => "Settings applied",
+                    Err(err) => {
+                        event!(Level::WARN, "Error saving settings: {}", err);
+                        "Settings applied, but could not be saved"
+                    }
+                };
+                self.applied = Some(settings);
+                self.refresh(world, notice, UiAction::ApplyOptions);
+                Trans::None
+            }
+            Some(UiAction::Back) => router::go(world, router::BACK),
+            _ => Trans::None,
+        }
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        self.revert(data.world);
+        self.screen.delete(data.world);
+    }
+}
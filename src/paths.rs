@@ -0,0 +1,32 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Directory for the player configuration, `$XDG_CONFIG_HOME/corylus` on Linux.
+pub fn user_config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("corylus"))
+}
+
+/// Directory for the player data like saves, `$XDG_DATA_HOME/corylus` on Linux.
+pub fn user_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("corylus"))
+}
+
+/// Write `content` to `path` without leaving a partially written file behind: it is written to a
+/// temporary file next to it first, then moved in place. Missing directories are created.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary_name = path.as_os_str().to_owned();
+    temporary_name.push(".tmp");
+    let temporary = PathBuf::from(temporary_name);
+    {
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temporary, path)
+}
@@ -0,0 +1,87 @@
+use amethyst::{
+    core::Transform,
+    ecs::prelude::{
+        Component, DenseVecStorage, Join, Read, ReadStorage, System, Write, WriteStorage,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    camera::CameraController,
+    gameplay::{random_position, GameplayConfig, Run},
+    player::Player,
+};
+
+/// A star for the player to collect.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct Pickup {
+    /// Score given when collected.
+    pub value: u32,
+    /// Sprite in the gameplay sheet.
+    pub sprite: usize,
+}
+
+impl Component for Pickup {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Collects the `Pickup`s the `Player` touches, adding to the score of the `Run`. Collected
+/// stars appear again somewhere else in the arena.
+#[derive(Debug, Default)]
+pub struct PickupSystem;
+
+impl<'a> System<'a> for PickupSystem {
+    type SystemData = (
+        Read<'a, GameplayConfig>,
+        Write<'a, Run>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Pickup>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, CameraController>,
+    );
+
+    #[instrument(
+        skip(config, run, players, pickups, transforms, cameras),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (config, mut run, players, pickups, mut transforms, mut cameras): Self::SystemData,
+    ) {
+        if run.over {
+            return;
+        }
+        let players = (&players, &transforms)
+            .join()
+            .map(|(_, transform)| (transform.translation().x, transform.translation().y))
+            .collect::<Vec<_>>();
+        let reach = (config.player_size + config.star_size) / 2.;
+
+        let mut collected = false;
+        for (pickup, transform) in (&pickups, &mut transforms).join() {
+            let (x, y) = (transform.translation().x, transform.translation().y);
+            if !players
+                .iter()
+                .any(|(player_x, player_y)| (player_x - x).hypot(player_y - y) < reach)
+            {
+                continue;
+            }
+            run.score += pickup.value;
+            run.collected += 1;
+            collected = true;
+            event!(Level::INFO, "Star collected, score: {}", run.score);
+
+            let (x, y) = random_position(&config.arena, config.star_size);
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+        }
+
+        if collected {
+            for camera in (&mut cameras).join() {
+                camera.shake(config.collect_shake);
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use amethyst::{
+    core::{math::Vector2, Time, Transform},
+    ecs::prelude::{Component, Join, NullStorage, Read, ReadStorage, System, WriteStorage},
+    input::{InputHandler, StringBindings},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{
+    bindings,
+    console::Console,
+    gamepad,
+    gameplay::{GameplayConfig, Run, TimeScale},
+};
+
+/// The entity moved by the player.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Player;
+
+impl Component for Player {
+    type Storage = NullStorage<Self>;
+}
+
+/// Moves the `Player` with the direction actions or the left stick, inside the arena.
+#[derive(Debug, Default)]
+pub struct PlayerSystem;
+
+impl<'a> System<'a> for PlayerSystem {
+    type SystemData = (
+        Read<'a, InputHandler<StringBindings>>,
+        Read<'a, Time>,
+        Read<'a, TimeScale>,
+        Read<'a, GameplayConfig>,
+        Read<'a, Run>,
+        Read<'a, Console>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+    );
+
+    #[instrument(
+        skip(input, time, time_scale, config, run, console, players, transforms),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (input, time, time_scale, config, run, console, players, mut transforms): Self::SystemData,
+    ) {
+        // the direction keys browse the history of the console
+        if run.over || console.captures_input() {
+            return;
+        }
+        let pressed = |action: &str| input.action_is_down(action).unwrap_or(false) as i8;
+        let keys = Vector2::new(
+            f32::from(pressed(bindings::RIGHT) - pressed(bindings::LEFT)),
+            f32::from(pressed(bindings::UP) - pressed(bindings::DOWN)),
+        );
+        // the stick is not only a direction, it moves slower when barely pushed
+        let direction = keys + gamepad::left_stick(&input);
+        if direction.x == 0. && direction.y == 0. {
+            return;
+        }
+        // diagonals are not faster
+        let direction = if direction.norm() > 1. {
+            direction.normalize()
+        } else {
+            direction
+        };
+        let movement = direction * config.player_speed * time_scale.delta(&time);
+
+        let margin = config.player_size / 2.;
+        let arena = &config.arena;
+        for (_, transform) in (&players, &mut transforms).join() {
+            let x = (transform.translation().x + movement.x)
+                .max(arena.min.0 + margin)
+                .min(arena.max.0 - margin);
+            let y = (transform.translation().y + movement.y)
+                .max(arena.min.1 + margin)
+                .min(arena.max.1 - margin);
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+        }
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use amethyst::{
+    config::Config, core::Time, ecs::World, prelude::*, shrev::EventChannel,
+    utils::application_root_dir, State, TransEvent,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    about::AboutScreen,
+    console::{ArgKind, Commands, Param},
+    controls::ControlsScreen,
+    game::Game,
+    game_over::GameOverScreen,
+    leaderboard::HighScoresScreen,
+    loading::LoadingScreen,
+    menu::MainMenu,
+    options::OptionsScreen,
+    pause::PauseMenuState,
+    slots::{SlotMode, SlotsScreen},
+    splash::SplashScreen,
+};
+
+/// Event sent by every screen to go back, handled as `Transition::Back` unless the screen
+/// overrides it.
+pub const BACK: &str = "back";
+
+type Screen = Box<dyn State<GameData<'static, 'static>, StateEvent>>;
+
+/// Build the screen with this name, this is the only place that knows about states.
+fn create_screen(name: &str) -> Option<Screen> {
+    match name {
+        "splash" => Some(Box::new(SplashScreen::default())),
+        "loading" => Some(Box::new(LoadingScreen::default())),
+        "main_menu" => Some(Box::new(MainMenu::default())),
+        "about" => Some(Box::new(AboutScreen::default())),
+        "controls" => Some(Box::new(ControlsScreen::default())),
+        "high_scores" => Some(Box::new(HighScoresScreen::default())),
+        "options" => Some(Box::new(OptionsScreen::default())),
+        "save" => Some(Box::new(SlotsScreen::new(SlotMode::Save))),
+        "load" => Some(Box::new(SlotsScreen::new(SlotMode::Load))),
+        "game" => Some(Box::new(Game::default())),
+        "pause" => Some(Box::new(PauseMenuState::default())),
+        "game_over" => Some(Box::new(GameOverScreen::default())),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum Transition {
+    /// Replace the current screen, going back returns to it.
+    Switch(String),
+    /// Display a screen over the current one, going back removes it.
+    Push(String),
+    /// Forget all screens and start again from this one.
+    Reset(String),
+    /// Return to the previous screen.
+    Back,
+    Quit,
+    /// Stay on the current screen, to ignore an event.
+    Stay,
+}
+
+impl Transition {
+    fn target(&self) -> Option<&str> {
+        match self {
+            Transition::Switch(screen) | Transition::Push(screen) | Transition::Reset(screen) => {
+                Some(screen)
+            }
+            Transition::Back | Transition::Quit | Transition::Stay => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScreenFlow {
+    /// Transitions from this screen, by event name.
+    #[serde(default)]
+    pub transitions: HashMap<String, Transition>,
+}
+
+/// The screens of the game and how to go from one to another, from `config/flow.ron`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Flow {
+    pub start: String,
+    pub screens: HashMap<String, ScreenFlow>,
+}
+
+impl Flow {
+    /// Screens that can't be reached from `start`.
+    pub fn unreachable_screens(&self) -> Vec<&str> {
+        let mut reached = HashSet::new();
+        let mut to_visit = VecDeque::new();
+        to_visit.push_back(self.start.as_str());
+        while let Some(screen) = to_visit.pop_front() {
+            if !reached.insert(screen) {
+                continue;
+            }
+            if let Some(flow) = self.screens.get(screen) {
+                to_visit.extend(flow.transitions.values().filter_map(Transition::target));
+            }
+        }
+        let mut unreachable = self
+            .screens
+            .keys()
+            .map(String::as_str)
+            .filter(|screen| !reached.contains(screen))
+            .collect::<Vec<_>>();
+        unreachable.sort();
+        unreachable
+    }
+
+    /// Screens used in the flow that are not declared, or that don't exist in the game.
+    pub fn unknown_screens(&self) -> Vec<&str> {
+        let mut unknown = std::iter::once(self.start.as_str())
+            .chain(self.screens.keys().map(String::as_str))
+            .chain(
+                self.screens
+                    .values()
+                    .flat_map(|flow| flow.transitions.values())
+                    .filter_map(Transition::target),
+            )
+            .filter(|screen| !self.screens.contains_key(*screen) || create_screen(screen).is_none())
+            .collect::<Vec<_>>();
+        unknown.sort();
+        unknown.dedup();
+        unknown
+    }
+}
+
+/// Follows the `Flow` and keeps track of the screens displayed to be able to go back.
+///
+/// Each layer of the stack matches a state in the amethyst state machine, and lists the screens
+/// that were switched through in this state.
+#[derive(Debug)]
+pub struct Router {
+    flow: Flow,
+    stack: Vec<Vec<String>>,
+    // frame during which steps were sent to the `TransEvent` channel: the stack is ahead of the
+    // state machine until they are applied at the end of the frame
+    pending_frame: Option<u64>,
+}
+
+impl Router {
+    pub fn new(flow: Flow) -> Self {
+        Self {
+            stack: vec![vec![flow.start.clone()]],
+            flow,
+            pending_frame: None,
+        }
+    }
+
+    fn is_pending(&self, frame: u64) -> bool {
+        self.pending_frame == Some(frame)
+    }
+
+    /// Load the flow from `config/flow.ron`, failing if it references unknown screens.
+    pub fn from_config() -> amethyst::Result<Self> {
+        let flow = Flow::load(application_root_dir()?.join("config/flow.ron"))?;
+        let unknown = flow.unknown_screens();
+        if !unknown.is_empty() {
+            return Err(amethyst::Error::from_string(format!(
+                "unknown screens in flow: {}",
+                unknown.join(", ")
+            )));
+        }
+        let unreachable = flow.unreachable_screens();
+        if !unreachable.is_empty() {
+            event!(
+                Level::WARN,
+                "unreachable screens in flow: {}",
+                unreachable.join(", ")
+            );
+        }
+        Ok(Self::new(flow))
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.stack
+            .last()
+            .and_then(|layer| layer.last())
+            .map(String::as_str)
+    }
+
+    /// The screen displayed by each state, from the bottom of the stack.
+    pub fn screens(&self) -> Vec<&str> {
+        self.stack
+            .iter()
+            .filter_map(|layer| layer.last())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The transition to the first screen.
+    pub fn start(&self) -> SimpleTrans {
+        Trans::Switch(create_screen(&self.flow.start).expect("flow is validated on load"))
+    }
+
+    fn find(&self, event_name: &str) -> Option<Transition> {
+        let screen = self.current()?;
+        let transition = self
+            .flow
+            .screens
+            .get(screen)
+            .and_then(|flow| flow.transitions.get(event_name))
+            .cloned();
+        if transition.is_none() && event_name == BACK {
+            Some(Transition::Back)
+        } else {
+            transition
+        }
+    }
+
+    /// Update the stack for `transition`, returning the steps the state machine has to take.
+    fn follow(&mut self, transition: Transition) -> Vec<Step> {
+        event!(Level::INFO, "{:?} -> {:?}", self.current(), transition);
+
+        match transition {
+            Transition::Switch(screen) => {
+                if let Some(layer) = self.stack.last_mut() {
+                    layer.push(screen.clone());
+                }
+                vec![Step::Switch(screen)]
+            }
+            Transition::Push(screen) => {
+                self.stack.push(vec![screen.clone()]);
+                vec![Step::Push(screen)]
+            }
+            Transition::Reset(screen) => {
+                // pop all states above the first one, then switch it
+                let mut steps = vec![Step::Pop; self.stack.len() - 1];
+                steps.push(Step::Switch(screen.clone()));
+                self.stack = vec![vec![screen]];
+                steps
+            }
+            Transition::Back => {
+                let layer_len = self.stack.last().map(Vec::len).unwrap_or(0);
+                if layer_len > 1 {
+                    let layer = self.stack.last_mut().expect("layer exists");
+                    layer.pop();
+                    let previous = layer.last().expect("layer is not empty").clone();
+                    vec![Step::Switch(previous)]
+                } else if self.stack.len() > 1 {
+                    self.stack.pop();
+                    vec![Step::Pop]
+                } else {
+                    event!(Level::INFO, "Nothing to go back to, quitting!");
+                    vec![Step::Quit]
+                }
+            }
+            Transition::Quit => vec![Step::Quit],
+            Transition::Stay => vec![],
+        }
+    }
+
+    /// Follow the transition for `event_name` from the current screen. Transitions that need
+    /// several steps are sent to the `TransEvent` channel, other transitions are ignored until
+    /// the end of the `frame`, when they are applied.
+    #[instrument(skip(self, trans_events), level = "info")]
+    fn transition(
+        &mut self,
+        event_name: &str,
+        frame: u64,
+        trans_events: &mut EventChannel<TransEvent<GameData<'static, 'static>, StateEvent>>,
+    ) -> SimpleTrans {
+        if self.is_pending(frame) {
+            event!(
+                Level::WARN,
+                "ignoring {} until the pending transitions are applied",
+                event_name
+            );
+            return Trans::None;
+        }
+        let transition = match self.find(event_name) {
+            Some(transition) => transition,
+            None => {
+                event!(
+                    Level::WARN,
+                    "no transition for {} from {:?}",
+                    event_name,
+                    self.current()
+                );
+                return Trans::None;
+            }
+        };
+
+        let mut steps = self.follow(transition);
+        if steps.is_empty() {
+            Trans::None
+        } else if steps.len() == 1 {
+            steps.remove(0).trans()
+        } else {
+            for step in steps {
+                trans_events.single_write(Box::new(move || step.trans()));
+            }
+            self.pending_frame = Some(frame);
+            Trans::None
+        }
+    }
+}
+
+/// A change of the state machine, with screens by name so that it can be sent as a `TransEvent`.
+#[derive(Debug, Clone)]
+enum Step {
+    Switch(String),
+    Push(String),
+    Pop,
+    Quit,
+}
+
+impl Step {
+    fn trans(&self) -> SimpleTrans {
+        match self {
+            Step::Switch(screen) => {
+                Trans::Switch(create_screen(screen).expect("flow is validated on load"))
+            }
+            Step::Push(screen) => {
+                Trans::Push(create_screen(screen).expect("flow is validated on load"))
+            }
+            Step::Pop => Trans::Pop,
+            Step::Quit => Trans::Quit,
+        }
+    }
+}
+
+/// Follow the transition for `event_name` from the current screen.
+pub fn go(world: &World, event_name: &str) -> SimpleTrans {
+    let frame = world.read_resource::<Time>().frame_number();
+    let mut trans_events =
+        world.write_resource::<EventChannel<TransEvent<GameData<'static, 'static>, StateEvent>>>();
+    world
+        .write_resource::<Router>()
+        .transition(event_name, frame, &mut trans_events)
+}
+
+/// Follow `transition` whatever the flow allows from the current screen. The state machine
+/// changes at the next frame.
+pub fn force(world: &World, transition: Transition) -> amethyst::Result<()> {
+    if let Some(screen) = transition.target() {
+        if create_screen(screen).is_none() {
+            return Err(amethyst::Error::from_string(format!(
+                "unknown screen: {}",
+                screen
+            )));
+        }
+    }
+    let frame = world.read_resource::<Time>().frame_number();
+    let mut router = world.write_resource::<Router>();
+    if router.is_pending(frame) {
+        return Err(amethyst::Error::from_string(
+            "another transition is pending, try again".to_string(),
+        ));
+    }
+    let steps = router.follow(transition);
+    router.pending_frame = Some(frame);
+    let mut trans_events =
+        world.write_resource::<EventChannel<TransEvent<GameData<'static, 'static>, StateEvent>>>();
+    for step in steps {
+        trans_events.single_write(Box::new(move || step.trans()));
+    }
+    Ok(())
+}
+
+/// Console commands to move between screens, ignoring the flow.
+fn register_commands(world: &World) {
+    let mut screens = world
+        .read_resource::<Router>()
+        .flow
+        .screens
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    screens.sort();
+    let screen = || vec![Param::new("screen", ArgKind::Choice(screens.clone()))];
+    let stack = |world: &World| {
+        format!(
+            "States: {}",
+            world.read_resource::<Router>().screens().join(" > ")
+        )
+    };
+
+    let mut commands = world.write_resource::<Commands>();
+    commands.register(
+        "state",
+        "Show the screen displayed by each state",
+        vec![],
+        move |world, _| Ok(stack(world)),
+    );
+    commands.register(
+        "state push",
+        "Display a screen over the current one",
+        screen(),
+        |world, args| {
+            let screen = args.text(0).unwrap_or_default().to_string();
+            force(world, Transition::Push(screen.clone()))?;
+            Ok(format!("Pushing {}", screen))
+        },
+    );
+    commands.register(
+        "state switch",
+        "Replace the current screen",
+        screen(),
+        |world, args| {
+            let screen = args.text(0).unwrap_or_default().to_string();
+            force(world, Transition::Switch(screen.clone()))?;
+            Ok(format!("Switching to {}", screen))
+        },
+    );
+    commands.register(
+        "state reset",
+        "Forget all screens and start again from one",
+        screen(),
+        |world, args| {
+            let screen = args.text(0).unwrap_or_default().to_string();
+            force(world, Transition::Reset(screen.clone()))?;
+            Ok(format!("Resetting to {}", screen))
+        },
+    );
+    commands.register(
+        "state back",
+        "Return to the previous screen",
+        vec![],
+        |world, _| {
+            force(world, Transition::Back)?;
+            Ok("Going back".to_string())
+        },
+    );
+}
+
+/// First state of the application, sets up what is shared by all screens then hands over to
+/// the start of the flow.
+#[derive(Debug, Default)]
+pub struct Boot;
+
+impl SimpleState for Boot {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        crate::audio::init(world);
+        crate::save::init(world);
+        crate::gameplay::init(world);
+        crate::settings::init(world);
+
+        register_commands(world);
+        crate::ui_scheme::register_commands(world);
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        data.world.read_resource::<Router>().start()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow() -> Flow {
+        Flow::load(concat!(env!("CARGO_MANIFEST_DIR"), "/config/flow.ron"))
+            .expect("config/flow.ron is valid")
+    }
+
+    #[test]
+    fn every_screen_is_reachable() {
+        assert_eq!(flow().unreachable_screens(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn every_screen_exists() {
+        assert_eq!(flow().unknown_screens(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn transitions_wait_for_pending_steps() {
+        let mut router = Router::new(flow());
+        router.stack = vec![
+            vec!["main_menu".to_string(), "game".to_string()],
+            vec!["pause".to_string()],
+        ];
+        let mut trans_events = EventChannel::new();
+
+        // pops the pause, then switches the game
+        let trans = router.transition("exit_to_menu", 1, &mut trans_events);
+        assert!(matches!(trans, Trans::None));
+        assert_eq!(router.screens(), vec!["main_menu"]);
+
+        // the states still are the game and the pause menu until the end of the frame
+        let trans = router.transition(BACK, 1, &mut trans_events);
+        assert!(matches!(trans, Trans::None));
+        assert_eq!(router.screens(), vec!["main_menu"]);
+
+        let trans = router.transition(BACK, 2, &mut trans_events);
+        assert!(matches!(trans, Trans::Quit));
+    }
+
+    #[test]
+    fn back_skips_the_splash() {
+        let mut router = Router::new(flow());
+        let trans = router.transition(BACK, 1, &mut EventChannel::new());
+        assert!(matches!(trans, Trans::Switch(_)));
+        assert_eq!(router.screens(), vec!["loading"]);
+    }
+
+    #[test]
+    fn back_is_ignored_while_loading() {
+        let mut router = Router::new(flow());
+        router.stack = vec![vec!["loading".to_string()]];
+        let mut trans_events = EventChannel::new();
+
+        let trans = router.transition(BACK, 1, &mut trans_events);
+        assert!(matches!(trans, Trans::None));
+        assert_eq!(router.screens(), vec!["loading"]);
+        // nothing is pending, the next event is handled
+        let trans = router.transition("done", 1, &mut trans_events);
+        assert!(matches!(trans, Trans::Switch(_)));
+        assert_eq!(router.screens(), vec!["main_menu"]);
+    }
+}
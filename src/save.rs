@@ -0,0 +1,401 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use amethyst::{
+    assets::{Directory, Loader},
+    core::Transform,
+    ecs::prelude::{Component, Entities, Join, NullStorage, ReadStorage, World, WorldExt},
+    prelude::*,
+};
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+    gameplay::Run,
+    paths,
+    pickup::Pickup,
+    player::Player,
+    screenshot::{Capture, Screenshots},
+};
+
+/// Version of the `SaveFile` format, to increase with a migration in `read` when a change needs
+/// more than new fields with defaults.
+pub const SAVE_VERSION: u32 = 1;
+
+/// Slots offered by the save and load screens.
+pub const SLOTS: &[&str] = &["1", "2", "3"];
+/// Asset source of the files in the user data directory, like the thumbnails of the saves.
+pub const USER_DATA_SOURCE: &str = "user_data";
+
+/// Marks the gameplay entities that are saved with the game. They are removed when the game
+/// stops.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Saved;
+
+impl Component for Saved {
+    type Storage = NullStorage<Self>;
+}
+
+/// Time spent playing the current game, in seconds.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Playtime(pub f64);
+
+/// Shown in the save and load screens, read without the entities of the save.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SlotMeta {
+    pub slot: String,
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub playtime: f64,
+    /// Screenshot of the game when it was saved, if one could be taken. Relative to the user data
+    /// directory, to load from `USER_DATA_SOURCE`.
+    #[serde(default)]
+    pub thumbnail: Option<PathBuf>,
+}
+
+impl SlotMeta {
+    pub fn saved_at(&self) -> String {
+        Local
+            .timestamp(self.saved_at as i64, 0)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    pub fn playtime(&self) -> String {
+        let seconds = self.playtime as u64;
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+/// The saved components of an entity marked `Saved`. Gameplay components that should survive a
+/// save are added here as optional fields.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SavedEntity {
+    pub transform: Option<Transform>,
+    pub player: Option<Player>,
+    pub pickup: Option<Pickup>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+// the start of a `SaveFile`, the rest of the file is skipped
+#[derive(Debug, Deserialize)]
+struct SaveMetaFile {
+    meta: SlotMeta,
+}
+
+/// A saved game, as written in `<user data>/saves/<slot>.ron`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub meta: SlotMeta,
+    pub playtime: Playtime,
+    /// Missing from saves made before runs existed, they get a new run.
+    #[serde(default)]
+    pub run: Option<Run>,
+    pub entities: Vec<SavedEntity>,
+}
+
+/// A save to restore when the game starts.
+#[derive(Debug)]
+pub struct PendingLoad(pub SaveFile);
+
+fn slot_path(slot: &str) -> Option<PathBuf> {
+    paths::user_data_dir().map(|dir| dir.join("saves").join(format!("{}.ron", slot)))
+}
+
+// the game when the save screen was opened, for the slot it is saved in
+fn next_thumbnail_path() -> Option<PathBuf> {
+    paths::user_data_dir().map(|dir| dir.join("saves").join("next_thumbnail.png"))
+}
+
+/// Set up what saving needs, before any screen can save or load a game.
+pub fn init(world: &mut World) {
+    world.register::<Saved>();
+    world.insert(Playtime::default());
+    if let Some(dir) = paths::user_data_dir() {
+        world
+            .write_resource::<Loader>()
+            .add_source(USER_DATA_SOURCE, Directory::new(dir));
+    }
+}
+
+/// Take the thumbnail of the next save from the game as it is now, before a screen covers it.
+pub fn request_thumbnail(world: &World) {
+    let path = match next_thumbnail_path() {
+        Some(path) => path,
+        None => return,
+    };
+    // an older one would be saved if this one is not captured in time
+    if let Err(err) = fs::remove_file(&path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            event!(Level::WARN, "Error removing {}: {}", path.display(), err);
+        }
+    }
+    let mut screenshots = world.write_resource::<Screenshots>();
+    if screenshots.is_supported() {
+        screenshots.request(Capture::thumbnail(path));
+    }
+}
+
+/// Copy the last requested thumbnail next to the save of `slot`, returning its path relative
+/// to the user data directory.
+fn save_thumbnail(slot: &str) -> Option<PathBuf> {
+    let next = next_thumbnail_path().filter(|path| path.exists())?;
+    let name = PathBuf::from("saves").join(format!("{}.png", slot));
+    let path = paths::user_data_dir()?.join(&name);
+    match fs::copy(&next, &path) {
+        Ok(_) => Some(name),
+        Err(err) => {
+            event!(
+                Level::WARN,
+                "Error saving thumbnail {}: {}",
+                path.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+/// The current game, ready to be written in `slot`.
+pub fn capture(world: &World, slot: &str) -> SaveFile {
+    let playtime = *world.read_resource::<Playtime>();
+    let run = world.read_resource::<Run>().clone();
+    let entities = world.exec(
+        |(entities, saved, transforms, players, pickups): (
+            Entities<'_>,
+            ReadStorage<'_, Saved>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Player>,
+            ReadStorage<'_, Pickup>,
+        )| {
+            (&entities, &saved)
+                .join()
+                .map(|(entity, _)| SavedEntity {
+                    transform: transforms.get(entity).cloned(),
+                    player: players.get(entity).cloned(),
+                    pickup: pickups.get(entity).cloned(),
+                })
+                .collect::<Vec<_>>()
+        },
+    );
+    let saved_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    SaveFile {
+        version: SAVE_VERSION,
+        meta: SlotMeta {
+            slot: slot.to_string(),
+            saved_at,
+            playtime: playtime.0,
+            thumbnail: None,
+        },
+        playtime,
+        run: Some(run),
+        entities,
+    }
+}
+
+/// Save the current game in `slot`.
+pub fn save(world: &World, slot: &str) -> amethyst::Result<SlotMeta> {
+    let path =
+        slot_path(slot).ok_or_else(|| amethyst::Error::from_string("no user data directory"))?;
+    let mut save = capture(world, slot);
+    save.meta.thumbnail = save_thumbnail(slot);
+    let content = ron::ser::to_string_pretty(&save, Default::default())?;
+    paths::write_atomic(&path, content.as_bytes())?;
+    event!(
+        Level::INFO,
+        "Saved {} entities to {}",
+        save.entities.len(),
+        path.display()
+    );
+    Ok(save.meta)
+}
+
+/// Read the game saved in `slot`.
+pub fn read(slot: &str) -> amethyst::Result<SaveFile> {
+    let path =
+        slot_path(slot).ok_or_else(|| amethyst::Error::from_string("no user data directory"))?;
+    parse(&std::fs::read_to_string(&path)?)
+}
+
+fn check_version(content: &str) -> amethyst::Result<()> {
+    let header = ron::de::from_str::<SaveHeader>(content)?;
+    if header.version > SAVE_VERSION {
+        return Err(amethyst::Error::from_string(format!(
+            "save is from a newer version ({})",
+            header.version
+        )));
+    }
+    Ok(())
+}
+
+fn parse(content: &str) -> amethyst::Result<SaveFile> {
+    check_version(content)?;
+    Ok(ron::de::from_str::<SaveFile>(content)?)
+}
+
+fn parse_meta(content: &str) -> amethyst::Result<SlotMeta> {
+    check_version(content)?;
+    Ok(ron::de::from_str::<SaveMetaFile>(content)?.meta)
+}
+
+/// Metadata of the game saved in `slot`, if there is a readable one.
+pub fn read_meta(slot: &str) -> Option<SlotMeta> {
+    let path = slot_path(slot).filter(|path| path.exists())?;
+    let meta = std::fs::read_to_string(&path)
+        .map_err(amethyst::Error::from)
+        .and_then(|content| parse_meta(&content));
+    match meta {
+        Ok(meta) => Some(meta),
+        Err(err) => {
+            event!(Level::WARN, "Error reading save {}: {}", slot, err);
+            None
+        }
+    }
+}
+
+/// Remove the saved entities and reset the saved resources, leaving an empty game.
+pub fn clear(world: &mut World) {
+    let saved = {
+        let entities = world.entities();
+        let saved = world.read_storage::<Saved>();
+        (&entities, &saved)
+            .join()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>()
+    };
+    if let Err(err) = world.delete_entities(&saved) {
+        event!(Level::WARN, "Error removing saved entities: {}", err);
+    }
+    world.insert(Playtime::default());
+}
+
+/// Replace the current game by `save`. The run is left as is when the save has none.
+pub fn restore(world: &mut World, save: SaveFile) {
+    clear(world);
+    event!(
+        Level::INFO,
+        "Restoring {} entities from slot {}",
+        save.entities.len(),
+        save.meta.slot
+    );
+    world.insert(save.playtime);
+    if let Some(run) = save.run {
+        world.insert(run);
+    }
+    for saved in save.entities {
+        let mut builder = world.create_entity().with(Saved);
+        if let Some(transform) = saved.transform {
+            builder = builder.with(transform);
+        }
+        if let Some(player) = saved.player {
+            builder = builder.with(player);
+        }
+        if let Some(pickup) = saved.pickup {
+            builder = builder.with(pickup);
+        }
+        builder.build();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::core::math::Vector3;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Saved>();
+        world.insert(Playtime::default());
+        world.register::<Transform>();
+        world.register::<Player>();
+        world.register::<Pickup>();
+        world.insert(Run::new(60.));
+        world
+    }
+
+    fn at(x: f32, y: f32) -> Transform {
+        let mut transform = Transform::default();
+        transform.set_translation(Vector3::new(x, y, 0.));
+        transform
+    }
+
+    fn to_ron(save: &SaveFile) -> String {
+        ron::ser::to_string_pretty(save, Default::default()).unwrap()
+    }
+
+    #[test]
+    fn restored_games_save_the_same() {
+        let mut world = world();
+        world.insert(Playtime(42.5));
+        world.write_resource::<Run>().score = 30;
+        world
+            .create_entity()
+            .with(Saved)
+            .with(Player)
+            .with(at(10., -20.))
+            .build();
+        world
+            .create_entity()
+            .with(Saved)
+            .with(Pickup {
+                value: 10,
+                sprite: 1,
+            })
+            .with(at(300., 200.))
+            .build();
+        // not saved
+        world.create_entity().with(at(0., 0.)).build();
+        let save = capture(&world, "1");
+        assert_eq!(save.entities.len(), 2);
+
+        let mut restored = self::world();
+        restored.create_entity().with(Saved).with(Player).build();
+        restore(&mut restored, parse(&to_ron(&save)).unwrap());
+        let mut saved_again = capture(&restored, "1");
+        assert_eq!(saved_again.playtime.0, 42.5);
+        assert_eq!(saved_again.run.as_ref().map(|run| run.score), Some(30));
+        saved_again.meta.saved_at = save.meta.saved_at;
+        assert_eq!(to_ron(&saved_again), to_ron(&save));
+    }
+
+    #[test]
+    fn meta_is_read_alone() {
+        let save = capture(&world(), "2");
+        let meta = parse_meta(&to_ron(&save)).unwrap();
+        assert_eq!(meta.slot, "2");
+        assert_eq!(meta.saved_at, save.meta.saved_at);
+    }
+
+    #[test]
+    fn newer_saves_are_rejected() {
+        let mut save = capture(&world(), "3");
+        save.version = SAVE_VERSION + 1;
+        let content = to_ron(&save);
+        assert!(parse(&content).is_err());
+        assert!(parse_meta(&content).is_err());
+
+        save.version = SAVE_VERSION;
+        assert!(parse(&to_ron(&save)).is_ok());
+    }
+}
@@ -0,0 +1,585 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use amethyst::{
+    core::Hidden,
+    derive::SystemDesc,
+    ecs::prelude::{
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    input::{InputEvent, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::UiTransform,
+};
+
+use chrono::Local;
+use tracing::{event, instrument, Level};
+
+use crate::{bindings, paths, toast::Toasts};
+
+/// Size save thumbnails are scaled down to.
+pub const THUMBNAIL_SIZE: (u32, u32) = (320, 180);
+// rendered frames to wait after hiding the UI, before it is gone from the last frame
+const HIDE_UI_FRAMES: u32 = 2;
+// frames to wait for a requested frame before giving up, none may come when nothing renders
+const READ_TIMEOUT_FRAMES: u32 = 60;
+
+/// Pixels of a rendered frame, 8 bit RGBA rows from the top.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// A copy fitting in `max_width` x `max_height`, keeping the aspect ratio. Frames are never
+    /// scaled up.
+    pub fn scaled_to_fit(&self, max_width: u32, max_height: u32) -> Frame {
+        let scale = (max_width as f32 / self.width as f32)
+            .min(max_height as f32 / self.height as f32)
+            .min(1.);
+        let width = ((self.width as f32 * scale).round() as u32).max(1);
+        let height = ((self.height as f32 * scale).round() as u32).max(1);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let source_y = (y * self.height / height) as usize;
+            for x in 0..width {
+                let source_x = (x * self.width / width) as usize;
+                let i = (source_y * self.width as usize + source_x) * 4;
+                pixels.extend_from_slice(&self.pixels[i..i + 4]);
+            }
+        }
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn save_png(&self, path: &Path) -> amethyst::Result<()> {
+        if self.pixels.len() != (self.width * self.height * 4) as usize {
+            return Err(amethyst::Error::from_string(format!(
+                "{} bytes for a {}x{} frame",
+                self.pixels.len(),
+                self.width,
+                self.height
+            )));
+        }
+        let mut content = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut content, self.width, self.height);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(&self.pixels)?;
+        }
+        paths::write_atomic(path, &content)?;
+        Ok(())
+    }
+}
+
+/// Reads back the frames rendered to the window.
+///
+/// It is given to the `Screenshots` by whoever can read the frames: the `WindowFrames` of the
+/// `RenderCapture` plugin, or a fake one in tests. Without one, captures fail with a message.
+pub trait FrameSource: Send + Sync {
+    /// Read back one of the next frames.
+    fn request(&mut self);
+
+    /// The frame read back since the last request, once it is there.
+    fn take(&mut self) -> Option<amethyst::Result<Frame>>;
+
+    /// Stop waiting for the requested frame.
+    fn cancel(&mut self);
+}
+
+/// A frame to capture, and what to do with it.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// Where to write the PNG.
+    pub path: PathBuf,
+    /// Take the frame without the UI.
+    pub hide_ui: bool,
+    /// Scale the frame down to fit in this size.
+    pub max_size: Option<(u32, u32)>,
+    /// Tell the player where it was saved, with a toast.
+    pub notify: bool,
+}
+
+impl Capture {
+    /// A screenshot in `<user data>/screenshots`, named after the current time.
+    pub fn screenshot(hide_ui: bool) -> Option<Self> {
+        let name = format!(
+            "corylus_{}.png",
+            Local::now().format("%Y-%m-%d_%H-%M-%S_%3f")
+        );
+        paths::user_data_dir().map(|dir| Self {
+            path: dir.join("screenshots").join(name),
+            hide_ui,
+            max_size: None,
+            notify: true,
+        })
+    }
+
+    /// A small picture of the game without its UI, like the thumbnails of the saves.
+    pub fn thumbnail(path: PathBuf) -> Self {
+        Self {
+            path,
+            hide_ui: true,
+            max_size: Some(THUMBNAIL_SIZE),
+            notify: false,
+        }
+    }
+}
+
+/// Captures waiting for the next frames, taken from the `FrameSource`. Anything can request one,
+/// whatever the current state.
+#[derive(Default)]
+pub struct Screenshots {
+    pending: Vec<Capture>,
+    source: Option<Box<dyn FrameSource>>,
+}
+
+impl fmt::Debug for Screenshots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Screenshots")
+            .field("pending", &self.pending)
+            .field("supported", &self.is_supported())
+            .finish()
+    }
+}
+
+impl Screenshots {
+    pub fn set_source(&mut self, source: impl FrameSource + 'static) {
+        self.source = Some(Box::new(source));
+    }
+
+    /// Can frames be captured.
+    pub fn is_supported(&self) -> bool {
+        self.source.is_some()
+    }
+
+    pub fn request(&mut self, capture: Capture) {
+        event!(Level::INFO, "Capture requested: {}", capture.path.display());
+        self.pending.push(capture);
+    }
+}
+
+/// Takes a screenshot with the screenshot actions, and handles the captures requested to the
+/// `Screenshots`: hides the UI for the ones that need it, then requests a frame from the
+/// `FrameSource` and writes it to each of them once it is read back.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(ScreenshotSystemDesc))]
+pub struct ScreenshotSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<InputEvent<StringBindings>>,
+    // UI elements hidden for the capture, with the frames rendered since
+    #[system_desc(skip)]
+    hidden_ui: Option<(Vec<Entity>, u32)>,
+    // captures waiting for the requested frame, with the frames waited so far
+    #[system_desc(skip)]
+    reading: Vec<Capture>,
+    #[system_desc(skip)]
+    read_frames: u32,
+}
+
+impl ScreenshotSystem {
+    pub fn new(reader_id: ReaderId<InputEvent<StringBindings>>) -> Self {
+        Self {
+            reader_id,
+            hidden_ui: None,
+            reading: vec![],
+            read_frames: 0,
+        }
+    }
+}
+
+fn save(capture: &Capture, frame: &amethyst::Result<Frame>, toasts: &mut Toasts) {
+    let saved = frame
+        .as_ref()
+        .map_err(|err| err.to_string())
+        .and_then(|frame| {
+            match capture.max_size {
+                Some((width, height)) => frame.scaled_to_fit(width, height),
+                None => frame.clone(),
+            }
+            .save_png(&capture.path)
+            .map_err(|err| err.to_string())
+        });
+    match saved {
+        Ok(()) => {
+            event!(Level::INFO, "Captured {}", capture.path.display());
+            if capture.notify {
+                toasts.show(format!("Screenshot saved to {}", capture.path.display()));
+            }
+        }
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "Error capturing {}: {}",
+                capture.path.display(),
+                err
+            );
+            if capture.notify {
+                toasts.show("Screenshot could not be saved");
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for ScreenshotSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Screenshots>,
+        Write<'a, Toasts>,
+        Read<'a, EventChannel<InputEvent<StringBindings>>>,
+        ReadStorage<'a, UiTransform>,
+        WriteStorage<'a, Hidden>,
+    );
+
+    #[instrument(
+        skip(entities, screenshots, toasts, input_events, ui_transforms, hiddens),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut screenshots,
+            mut toasts,
+            input_events,
+            ui_transforms,
+            mut hiddens,
+        ): Self::SystemData,
+    ) {
+        // both actions are pressed for the one without the UI, it takes precedence
+        let mut requested = None;
+        for input_event in input_events.read(&mut self.reader_id) {
+            if let InputEvent::ActionPressed(action) = input_event {
+                if action == bindings::SCREENSHOT {
+                    requested = requested.or(Some(false));
+                } else if action == bindings::SCREENSHOT_WITHOUT_UI {
+                    requested = Some(true);
+                }
+            }
+        }
+        if let Some(hide_ui) = requested {
+            match Capture::screenshot(hide_ui) {
+                Some(capture) => screenshots.request(capture),
+                None => toasts.show("Screenshots need a user data directory"),
+            }
+        }
+        let Screenshots { pending, source } = &mut *screenshots;
+
+        if !self.reading.is_empty() {
+            let frame = match source.as_mut().and_then(|source| source.take()) {
+                Some(frame) => frame,
+                None if self.read_frames < READ_TIMEOUT_FRAMES => {
+                    self.read_frames += 1;
+                    return;
+                }
+                None => {
+                    if let Some(source) = source.as_mut() {
+                        source.cancel();
+                    }
+                    Err(amethyst::Error::from_string(format!(
+                        "no frame read back after {} frames",
+                        READ_TIMEOUT_FRAMES
+                    )))
+                }
+            };
+            self.read_frames = 0;
+            for capture in self.reading.drain(..) {
+                save(&capture, &frame, &mut toasts);
+            }
+            if let Some((hidden, _)) = self.hidden_ui.take() {
+                for entity in hidden {
+                    hiddens.remove(entity);
+                }
+            }
+            return;
+        }
+
+        if pending.is_empty() {
+            return;
+        }
+        let source = match source {
+            Some(source) => source,
+            None => {
+                for capture in pending.drain(..) {
+                    event!(
+                        Level::WARN,
+                        "No frame source, {} is not captured",
+                        capture.path.display()
+                    );
+                    if capture.notify {
+                        toasts.show("Screenshots are not supported by this renderer");
+                    }
+                }
+                return;
+            }
+        };
+
+        if pending.iter().any(|capture| capture.hide_ui) {
+            let (_, frames) = self.hidden_ui.get_or_insert_with(|| {
+                let hidden = (&entities, &ui_transforms, !&hiddens)
+                    .join()
+                    .map(|(entity, _, _)| entity)
+                    .collect::<Vec<_>>();
+                for entity in &hidden {
+                    let _ = hiddens.insert(*entity, Hidden);
+                }
+                (hidden, 0)
+            });
+            if *frames < HIDE_UI_FRAMES {
+                *frames += 1;
+                return;
+            }
+        }
+
+        source.request();
+        self.reading.append(pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        process,
+        sync::{Arc, Mutex},
+    };
+
+    use amethyst::{
+        core::SystemDesc,
+        ecs::{Builder, RunNow, World, WorldExt},
+        ui::Anchor,
+    };
+
+    use super::*;
+
+    /// Gives `frame` the frame after it is requested, as a renderer would. Without one, it never
+    /// answers, like a renderer that stopped drawing.
+    #[derive(Debug, Clone)]
+    struct FakeSource {
+        frame: Option<Frame>,
+        requests: Arc<Mutex<u32>>,
+        requested: bool,
+    }
+
+    impl FrameSource for FakeSource {
+        fn request(&mut self) {
+            *self.requests.lock().unwrap() += 1;
+            self.requested = true;
+        }
+
+        fn take(&mut self) -> Option<amethyst::Result<Frame>> {
+            let frame = self.frame.clone().filter(|_| self.requested)?;
+            self.requested = false;
+            Some(Ok(frame))
+        }
+
+        fn cancel(&mut self) {
+            self.requested = false;
+        }
+    }
+
+    /// A gradient, so a wrong scaling shows in the pixels.
+    fn frame(width: u32, height: u32) -> Frame {
+        let mut pixels = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend_from_slice(&[x as u8, y as u8, 0, 255]);
+            }
+        }
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("corylus_test_{}", process::id()))
+            .join(name)
+    }
+
+    fn png_size(path: &Path) -> (u32, u32) {
+        let decoder = png::Decoder::new(File::open(path).expect("the PNG is written"));
+        let (info, _) = decoder.read_info().expect("the PNG is valid");
+        (info.width, info.height)
+    }
+
+    struct Simulation {
+        world: World,
+        system: ScreenshotSystem,
+        requests: Arc<Mutex<u32>>,
+    }
+
+    impl Simulation {
+        fn new(frame: Option<Frame>) -> Self {
+            let mut world = World::new();
+            let system = ScreenshotSystemDesc::default().build(&mut world);
+            let requests = Arc::new(Mutex::new(0));
+            world
+                .write_resource::<Screenshots>()
+                .set_source(FakeSource {
+                    frame,
+                    requests: requests.clone(),
+                    requested: false,
+                });
+            Self {
+                world,
+                system,
+                requests,
+            }
+        }
+
+        fn run(&mut self) {
+            self.system.run_now(&self.world);
+        }
+
+        fn requests(&self) -> u32 {
+            *self.requests.lock().unwrap()
+        }
+
+        fn hidden(&self, entity: Entity) -> bool {
+            self.world.read_storage::<Hidden>().contains(entity)
+        }
+
+        fn create_ui(&mut self) -> Entity {
+            self.world
+                .create_entity()
+                .with(UiTransform::new(
+                    "button".to_string(),
+                    Anchor::Middle,
+                    Anchor::Middle,
+                    0.,
+                    0.,
+                    0.,
+                    10.,
+                    10.,
+                ))
+                .build()
+        }
+    }
+
+    #[test]
+    fn scaled_frames_fit_and_keep_their_aspect_ratio() {
+        let scaled = frame(1280, 720).scaled_to_fit(THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1);
+        assert_eq!((scaled.width, scaled.height), THUMBNAIL_SIZE);
+        assert_eq!(scaled.pixels.len(), (320 * 180 * 4) as usize);
+
+        let tall = frame(600, 900).scaled_to_fit(320, 180);
+        assert_eq!((tall.width, tall.height), (120, 180));
+        // never scaled up
+        let small = frame(100, 50).scaled_to_fit(320, 180);
+        assert_eq!((small.width, small.height), (100, 50));
+        assert_eq!(small.pixels, frame(100, 50).pixels);
+    }
+
+    #[test]
+    fn captures_write_the_requested_frame() {
+        let mut simulation = Simulation::new(Some(frame(64, 32)));
+        let path = temporary_path("capture.png");
+        let _ = fs::remove_file(&path);
+        simulation
+            .world
+            .write_resource::<Screenshots>()
+            .request(Capture {
+                path: path.clone(),
+                hide_ui: false,
+                max_size: None,
+                notify: true,
+            });
+
+        simulation.run();
+        assert_eq!(simulation.requests(), 1);
+        assert!(!path.exists());
+        simulation.run();
+        assert_eq!(png_size(&path), (64, 32));
+        // nothing left to capture
+        simulation.run();
+        assert_eq!(simulation.requests(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn thumbnails_hide_the_ui_then_restore_it() {
+        let mut simulation = Simulation::new(Some(frame(1280, 720)));
+        let shown = simulation.create_ui();
+        let hidden = simulation.create_ui();
+        simulation
+            .world
+            .write_storage::<Hidden>()
+            .insert(hidden, Hidden)
+            .unwrap();
+        let path = temporary_path("thumbnail.png");
+        simulation
+            .world
+            .write_resource::<Screenshots>()
+            .request(Capture::thumbnail(path.clone()));
+
+        // the UI is gone from the frames rendered before the request
+        for _ in 0..HIDE_UI_FRAMES {
+            simulation.run();
+            assert!(simulation.hidden(shown));
+            assert_eq!(simulation.requests(), 0);
+        }
+        simulation.run();
+        assert_eq!(simulation.requests(), 1);
+        assert!(simulation.hidden(shown));
+        simulation.run();
+
+        let (width, height) = png_size(&path);
+        assert!(width <= THUMBNAIL_SIZE.0 && height <= THUMBNAIL_SIZE.1);
+        assert!(!simulation.hidden(shown));
+        // hidden by someone else, it stays hidden
+        assert!(simulation.hidden(hidden));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn captures_give_up_when_no_frame_comes() {
+        let mut simulation = Simulation::new(None);
+        let shown = simulation.create_ui();
+        let path = temporary_path("never.png");
+        simulation
+            .world
+            .write_resource::<Screenshots>()
+            .request(Capture::thumbnail(path.clone()));
+
+        for _ in 0..=HIDE_UI_FRAMES + READ_TIMEOUT_FRAMES {
+            simulation.run();
+            assert!(simulation.hidden(shown));
+        }
+        simulation.run();
+        assert!(!simulation.hidden(shown));
+        assert!(!path.exists());
+
+        // the next capture is not stuck behind it
+        simulation
+            .world
+            .write_resource::<Screenshots>()
+            .request(Capture::thumbnail(path));
+        for _ in 0..=HIDE_UI_FRAMES {
+            simulation.run();
+        }
+        assert_eq!(simulation.requests(), 2);
+    }
+
+    #[test]
+    fn captures_without_a_source_are_dropped() {
+        let mut world = World::new();
+        let mut system = ScreenshotSystemDesc::default().build(&mut world);
+        world
+            .write_resource::<Screenshots>()
+            .request(Capture::thumbnail(temporary_path("unsupported.png")));
+        system.run_now(&world);
+        assert!(world.read_resource::<Screenshots>().pending.is_empty());
+    }
+}
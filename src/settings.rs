@@ -0,0 +1,330 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use amethyst::{
+    core::frame_limiter::{FrameLimiter, FrameRateLimitStrategy},
+    prelude::*,
+    window::{DisplayConfig, Window},
+    winit::dpi::LogicalSize,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{audio::AudioConfig, i18n::Translations};
+
+/// Window sizes offered in the options.
+pub const RESOLUTIONS: &[(u32, u32)] = &[
+    (1024, 768),
+    (1280, 720),
+    (1280, 1024),
+    (1600, 900),
+    (1920, 1080),
+];
+/// Frame rates offered in the options for when the window is unfocused.
+pub const BACKGROUND_FRAME_RATES: &[u32] = &[5, 15, 30];
+/// UI scales offered in the options.
+pub const UI_SCALES: &[f32] = &[0.8, 1., 1.2];
+/// Languages with a translation in `assets/i18n`, by code with their name.
+pub const LANGUAGES: &[(&str, &str)] = &[("en", "English"), ("fr", "Français")];
+
+// frame rate of the limiter, the renderer picks its present mode itself so there is no vsync
+const LIMITED_FPS: u32 = 60;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub resolution: (u32, u32),
+    pub fullscreen: bool,
+    /// Limit the frame rate to 60 frames per second.
+    pub limit_fps: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            resolution: RESOLUTIONS[0],
+            fullscreen: false,
+            limit_fps: true,
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// Display settings matching the window described in `config/display.ron`.
+    pub fn from_display_config(config: &DisplayConfig) -> Self {
+        Self {
+            resolution: config.dimensions.unwrap_or(RESOLUTIONS[0]),
+            fullscreen: config.fullscreen.is_some(),
+            ..Default::default()
+        }
+    }
+
+    /// Reconfigure the window and frame limiter to match these settings.
+    pub fn apply(&self, world: &World) {
+        if let Some(window) = world.try_fetch::<Window>() {
+            if self.fullscreen {
+                window.set_fullscreen(Some(window.get_current_monitor()));
+            } else {
+                window.set_fullscreen(None);
+                let (width, height) = self.resolution;
+                window.set_inner_size(LogicalSize::new(width.into(), height.into()));
+            }
+        } else {
+            event!(Level::WARN, "No window to apply display settings to");
+        }
+
+        *world.write_resource::<FrameLimiter>() = self.frame_limiter();
+    }
+
+    pub fn frame_limiter(&self) -> FrameLimiter {
+        if self.limit_fps {
+            limit_frame_rate(LIMITED_FPS)
+        } else {
+            FrameLimiter::new(FrameRateLimitStrategy::Unlimited, LIMITED_FPS)
+        }
+    }
+}
+
+pub fn limit_frame_rate(fps: u32) -> FrameLimiter {
+    FrameLimiter::new(
+        FrameRateLimitStrategy::SleepAndYield(Duration::from_millis(2)),
+        fps,
+    )
+}
+
+/// What happens when the window loses focus or is minimized.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UnfocusedSettings {
+    /// Open the pause menu when playing.
+    pub pause: bool,
+    pub mute: bool,
+    /// Frame rate limit, `None` to keep the usual one.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for UnfocusedSettings {
+    fn default() -> Self {
+        Self {
+            pause: true,
+            mute: true,
+            frame_rate: Some(BACKGROUND_FRAME_RATES[1]),
+        }
+    }
+}
+
+/// Version of the `UserSettings` format, to increase with a migration in `UserSettings::migrate`
+/// when a change needs more than new fields with defaults.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Player preferences, changed from the options screen and saved in the player config.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// Files written before settings were versioned have none, and are version 0.
+    #[serde(default)]
+    pub version: u32,
+    pub display: DisplaySettings,
+    pub audio: AudioConfig,
+    pub unfocused: UnfocusedSettings,
+    /// Factor applied to the size of menus.
+    pub ui_scale: f32,
+    /// Code of the language of the UI, one of `LANGUAGES`.
+    pub language: String,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            display: Default::default(),
+            audio: Default::default(),
+            unfocused: Default::default(),
+            ui_scale: 1.,
+            language: LANGUAGES[0].0.to_string(),
+        }
+    }
+}
+
+fn user_settings_path() -> Option<PathBuf> {
+    crate::paths::user_config_dir().map(|dir| dir.join("settings.ron"))
+}
+
+impl UserSettings {
+    /// Settings from the player config, or `defaults` if they have none.
+    pub fn load_or(defaults: UserSettings) -> Self {
+        match user_settings_path().filter(|path| path.exists()) {
+            Some(path) => Self::load_from(&path, defaults),
+            None => defaults,
+        }
+    }
+
+    /// Settings from the file at `path`, or `defaults` if it can't be read. A file that can't be
+    /// parsed is kept aside and replaced by the defaults.
+    fn load_from(path: &Path, defaults: UserSettings) -> Self {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                event!(
+                    Level::WARN,
+                    "Error reading settings from {}, using defaults: {}",
+                    path.display(),
+                    err
+                );
+                return defaults;
+            }
+        };
+        match ron::de::from_str::<UserSettings>(&content) {
+            Ok(settings) => settings.migrate(),
+            Err(err) => {
+                event!(
+                    Level::WARN,
+                    "Error parsing settings from {}, using defaults: {}",
+                    path.display(),
+                    err
+                );
+                let corrupt = path.with_extension("ron.corrupt");
+                if let Err(err) = std::fs::rename(path, &corrupt) {
+                    event!(Level::WARN, "Error moving {}: {}", path.display(), err);
+                }
+                defaults
+            }
+        }
+    }
+
+    /// Bring settings saved by an older version of the game up to date.
+    fn migrate(mut self) -> Self {
+        if self.version > SETTINGS_VERSION {
+            event!(
+                Level::WARN,
+                "Settings are from a newer version ({}), some may be ignored",
+                self.version
+            );
+        }
+        // version 0 has the same fields as version 1
+        if self.version < 1 {
+            self.version = 1;
+        }
+        self
+    }
+
+    /// Save the settings in the player config.
+    pub fn save(&self) -> amethyst::Result<()> {
+        let path = user_settings_path()
+            .ok_or_else(|| amethyst::Error::from_string("no user config directory"))?;
+        let content = ron::ser::to_string_pretty(self, Default::default())?;
+        crate::paths::write_atomic(&path, content.as_bytes())?;
+        event!(Level::INFO, "Saved settings to {}", path.display());
+        Ok(())
+    }
+
+    /// Make these the current settings. Only what changed since `previous` is applied, everything
+    /// is when there is none.
+    pub fn apply(self, world: &mut World, previous: Option<&UserSettings>) {
+        if previous.map_or(true, |previous| previous.display != self.display) {
+            self.display.apply(world);
+        }
+        if previous.map_or(true, |previous| previous.language != self.language) {
+            let translations = Translations::for_language(&self.language);
+            world.insert(translations);
+        }
+        world.insert(self);
+    }
+}
+
+/// Apply the settings inserted at startup.
+pub fn init(world: &mut World) {
+    let settings = world.read_resource::<UserSettings>().clone();
+    settings.apply(world, None);
+}
+
+/// Next value after `current` in `values`, going back to the first one after the last.
+pub fn cycle<T: PartialEq + Clone>(values: &[T], current: &T) -> T {
+    let next = values
+        .iter()
+        .position(|value| value == current)
+        .map(|index| (index + 1) % values.len())
+        .unwrap_or(0);
+    values[next].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+
+    fn temporary_file(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("corylus_test_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn defaults() -> UserSettings {
+        UserSettings {
+            ui_scale: 1.2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn corrupt_settings_are_set_aside() {
+        let path = temporary_file("corrupt.ron", "(display: (fullscreen: maybe))");
+        let corrupt = path.with_extension("ron.corrupt");
+        let _ = fs::remove_file(&corrupt);
+
+        assert_eq!(UserSettings::load_from(&path, defaults()), defaults());
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&corrupt).unwrap(),
+            "(display: (fullscreen: maybe))"
+        );
+        fs::remove_file(&corrupt).unwrap();
+    }
+
+    #[test]
+    fn unreadable_settings_are_left_in_place() {
+        let dir = std::env::temp_dir().join(format!("corylus_test_{}", process::id()));
+        // a directory can't be read as a file
+        let path = dir.join("unreadable.ron");
+        fs::create_dir_all(&path).unwrap();
+
+        assert_eq!(UserSettings::load_from(&path, defaults()), defaults());
+        assert!(path.is_dir());
+        assert!(!path.with_extension("ron.corrupt").exists());
+        fs::remove_dir(&path).unwrap();
+    }
+
+    #[test]
+    fn version_0_is_migrated() {
+        // written before settings were versioned
+        let path = temporary_file(
+            "version_0.ron",
+            "(display: (fullscreen: true), ui_scale: 0.8)",
+        );
+        let settings = UserSettings::load_from(&path, defaults());
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(settings.display.fullscreen);
+        assert_eq!(settings.ui_scale, 0.8);
+        // missing fields get their defaults, not the given ones
+        assert_eq!(settings.language, UserSettings::default().language);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let mut settings = UserSettings::default();
+        settings.display.limit_fps = false;
+        settings.unfocused.frame_rate = None;
+        settings.language = "fr".to_string();
+        let content = ron::ser::to_string_pretty(&settings, Default::default()).unwrap();
+        let path = temporary_file("round_trip.ron", &content);
+        assert_eq!(UserSettings::load_from(&path, defaults()), settings);
+        fs::remove_file(&path).unwrap();
+    }
+}
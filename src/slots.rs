@@ -0,0 +1,241 @@
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    ecs::prelude::Entity,
+    input::is_close_requested,
+    prelude::*,
+    renderer::{ImageFormat, Texture},
+    ui::{Anchor, UiEvent, UiEventType, UiImage, UiTransform},
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::{
+    action::{ui_action, UiAction},
+    bindings, console,
+    i18n::Translations,
+    paths, router,
+    save::{self, PendingLoad, SlotMeta, SLOTS},
+    ui_scheme::{Menu, MenuButton, MenuScreen},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlotMode {
+    Save,
+    Load,
+}
+
+/// Lists the save slots with when they were saved and the time played. Activating a slot saves
+/// the current game in it, or loads it, depending on the mode. The screenshot of the highlighted
+/// slot is displayed when there is one.
+#[derive(Debug)]
+pub struct SlotsScreen {
+    mode: SlotMode,
+    slots: Vec<(String, Option<SlotMeta>)>,
+    screen: MenuScreen,
+    thumbnail: Option<Entity>,
+}
+
+impl SlotsScreen {
+    pub fn new(mode: SlotMode) -> Self {
+        Self {
+            mode,
+            slots: vec![],
+            screen: MenuScreen::default(),
+            thumbnail: None,
+        }
+    }
+
+    fn slot_action(&self, slot: &str) -> UiAction {
+        match self.mode {
+            SlotMode::Save => UiAction::SaveSlot(slot.to_string()),
+            SlotMode::Load => UiAction::LoadSlot(slot.to_string()),
+        }
+    }
+
+    fn delete_ui(&mut self, world: &mut World) {
+        self.screen.delete(world);
+        if let Some(thumbnail) = self.thumbnail.take() {
+            world
+                .delete_entity(thumbnail)
+                .expect("Failed to remove SlotsScreen thumbnail");
+        }
+    }
+
+    /// Read the slots again and rebuild the screen, then focus `focused`.
+    fn refresh(&mut self, world: &mut World, notice: &str, focused: UiAction) {
+        self.delete_ui(world);
+        self.slots = SLOTS
+            .iter()
+            .map(|slot| (slot.to_string(), save::read_meta(slot)))
+            .collect();
+
+        let mut buttons = {
+            let translations = world.read_resource::<Translations>();
+            self.slots
+                .iter()
+                .map(|(slot, meta)| {
+                    let description = match meta {
+                        Some(meta) => format!(
+                            "{} - {} {}",
+                            meta.saved_at(),
+                            meta.playtime(),
+                            translations.tr("played")
+                        ),
+                        None => translations.tr("Empty").to_string(),
+                    };
+                    MenuButton::new(
+                        &format!("{} {}: {}", translations.tr("Slot"), slot, description),
+                        &format!("slot_{}", slot),
+                        self.slot_action(slot),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        buttons.push(MenuButton::new("Back", "slots_back", UiAction::Back));
+
+        let menu = Menu {
+            button_size: (900., 80.),
+            ..Menu::new("slots", buttons)
+        };
+        self.screen.show(world, menu, notice, &focused);
+    }
+
+    /// Display the screenshot of the slot of this UI element, if it has one.
+    fn show_thumbnail(&mut self, world: &mut World, target: Entity) {
+        if let Some(thumbnail) = self.thumbnail.take() {
+            world
+                .delete_entity(thumbnail)
+                .expect("Failed to remove SlotsScreen thumbnail");
+        }
+        let slot = match world.read_storage::<UiAction>().get(target) {
+            Some(UiAction::SaveSlot(slot)) | Some(UiAction::LoadSlot(slot)) => slot.clone(),
+            _ => return,
+        };
+        let path = self
+            .slots
+            .iter()
+            .find(|(name, _)| *name == slot)
+            .and_then(|(_, meta)| meta.as_ref())
+            .and_then(|meta| meta.thumbnail.clone())
+            .filter(|path| paths::user_data_dir().map_or(false, |dir| dir.join(path).exists()));
+        if let Some(path) = path {
+            let image = world.read_resource::<Loader>().load_from(
+                path.to_string_lossy(),
+                ImageFormat::default(),
+                save::USER_DATA_SOURCE,
+                (),
+                &world.read_resource::<AssetStorage<Texture>>(),
+            );
+            self.thumbnail = Some(
+                world
+                    .create_entity()
+                    .with(UiTransform::new(
+                        "slots_thumbnail".to_string(),
+                        Anchor::TopRight,
+                        Anchor::TopRight,
+                        -20.,
+                        -60.,
+                        1.,
+                        320.,
+                        180.,
+                    ))
+                    .with(UiImage::Texture(image))
+                    .build(),
+            );
+        }
+    }
+
+    fn save(&mut self, world: &mut World, slot: String) {
+        let notice = match save::save(world, &slot) {
+            Ok(_) => "Game saved",
+            Err(err) => {
+                event!(Level::ERROR, "Error saving slot {}: {}", slot, err);
+                "The game could not be saved"
+            }
+        };
+        self.refresh(world, notice, UiAction::SaveSlot(slot));
+    }
+
+    fn load(&mut self, world: &mut World, slot: String) -> SimpleTrans {
+        let has_save = self
+            .slots
+            .iter()
+            .any(|(name, meta)| *name == slot && meta.is_some());
+        if !has_save {
+            self.refresh(world, "This slot is empty", UiAction::LoadSlot(slot));
+            return Trans::None;
+        }
+        match save::read(&slot) {
+            Ok(save) => {
+                world.insert(PendingLoad(save));
+                router::go(world, "load_game")
+            }
+            Err(err) => {
+                event!(Level::ERROR, "Error loading slot {}: {}", slot, err);
+                self.refresh(
+                    world,
+                    "The game could not be loaded",
+                    UiAction::LoadSlot(slot),
+                );
+                Trans::None
+            }
+        }
+    }
+}
+
+impl SimpleState for SlotsScreen {
+    #[instrument(skip(data), level = "info")]
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let notice = match self.mode {
+            SlotMode::Save => "Choose a slot to save the game",
+            SlotMode::Load => "Choose a game to load",
+        };
+        let first = self.slot_action(SLOTS[0]);
+        self.refresh(data.world, notice, first);
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        let world = data.world;
+
+        match &event {
+            StateEvent::Window(event) if is_close_requested(&event) => {
+                event!(Level::INFO, "Quitting Application!");
+                return Trans::Quit;
+            }
+            StateEvent::Ui(UiEvent {
+                event_type: UiEventType::HoverStart,
+                target,
+            }) => {
+                self.show_thumbnail(world, *target);
+                return Trans::None;
+            }
+            _ => (),
+        }
+        if console::is_typing(world, &event) {
+            return Trans::None;
+        }
+        if bindings::is_action(&event, bindings::BACK) {
+            return router::go(world, router::BACK);
+        }
+
+        match ui_action(world, &event) {
+            Some(UiAction::SaveSlot(slot)) => {
+                self.save(world, slot);
+                Trans::None
+            }
+            Some(UiAction::LoadSlot(slot)) => self.load(world, slot),
+            Some(UiAction::Back) => router::go(world, router::BACK),
+            _ => Trans::None,
+        }
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        self.delete_ui(data.world);
+    }
+}
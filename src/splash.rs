@@ -1,83 +1,243 @@
 use amethyst::{
+    config::Config,
+    core::Time,
     ecs::prelude::Entity,
-    input::{is_close_requested, is_key_down, is_mouse_button_down},
+    input::{is_close_requested, is_mouse_button_down},
     prelude::*,
-    ui::{Anchor, UiImage, UiTransform},
-    winit::{MouseButton, VirtualKeyCode},
+    ui::{Anchor, Stretch, UiImage, UiTransform},
+    utils::application_root_dir,
+    winit::MouseButton,
 };
 
+use serde::{Deserialize, Serialize};
 use tracing::{event, instrument, Level};
 
-use crate::ui_scheme;
+use crate::{bindings, console, router, ui_scheme};
+
+// longest frame taken into account, so that a slow first frame doesn't skip a logo
+const MAX_FRAME_DURATION: f32 = 0.1;
+
+/// What skipping does while a logo is displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum SkipPolicy {
+    Unskippable,
+    /// Go to the next logo.
+    Current,
+    /// Skip all remaining logos.
+    All,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SplashLogo {
+    /// Path to the image, relative to the assets folder.
+    pub image: String,
+    pub width: f32,
+    pub height: f32,
+    /// Total display time in seconds, fades included.
+    pub duration: f32,
+    #[serde(default)]
+    pub fade_in: f32,
+    #[serde(default)]
+    pub fade_out: f32,
+    pub skip: SkipPolicy,
+}
+
+impl SplashLogo {
+    /// Opacity of the logo, `elapsed` seconds after it started being displayed.
+    fn opacity(&self, elapsed: f32) -> f32 {
+        let fade_in = if self.fade_in > 0. {
+            elapsed / self.fade_in
+        } else {
+            1.
+        };
+        let fade_out = if self.fade_out > 0. {
+            (self.duration - elapsed) / self.fade_out
+        } else {
+            1.
+        };
+        fade_in.min(fade_out).max(0.).min(1.)
+    }
+}
+
+/// Logos displayed in order when starting the game, from `config/splash.ron`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SplashConfig {
+    pub logos: Vec<SplashLogo>,
+}
+
+impl Default for SplashConfig {
+    fn default() -> Self {
+        SplashConfig {
+            logos: vec![SplashLogo {
+                image: "texture/logo.png".to_string(),
+                width: 250.,
+                height: 250.,
+                duration: 1.,
+                fade_in: 0.,
+                fade_out: 0.,
+                skip: SkipPolicy::All,
+            }],
+        }
+    }
+}
 
 #[derive(Default, Debug)]
 pub struct SplashScreen {
+    config: SplashConfig,
+    current: usize,
+    elapsed: f32,
     ui_handle: Option<Entity>,
-    frame_displayed: u32,
+    // black cover over the logo, its transparency makes the logo fade
+    cover: Option<Entity>,
 }
 
-impl SimpleState for SplashScreen {
-    #[instrument(skip(data), level = "info")]
-    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        let world = data.world;
+impl SplashScreen {
+    fn display(&mut self, world: &mut World) {
+        self.remove(world);
+        self.elapsed = 0.;
 
-        let image = UiImage::Texture(ui_scheme::load_image(world, "texture/logo.png"));
+        let logo = match self.config.logos.get(self.current) {
+            Some(logo) => logo,
+            None => return,
+        };
+        let image = UiImage::Texture(ui_scheme::load_image(world, &logo.image));
         let transform = UiTransform::new(
-            "logo".to_string(),
+            format!("logo_{}", self.current),
             Anchor::Middle,
             Anchor::Middle,
             0.,
             0.,
             0.,
-            250.,
-            250.,
+            logo.width,
+            logo.height,
         );
-
         self.ui_handle = Some(world.create_entity().with(image).with(transform).build());
+
+        let cover = UiTransform::new(
+            "logo_cover".to_string(),
+            Anchor::Middle,
+            Anchor::Middle,
+            0.,
+            0.,
+            1.,
+            20.,
+            20.,
+        )
+        .with_stretch(Stretch::XY {
+            x_margin: 0.,
+            y_margin: 0.,
+            keep_aspect_ratio: false,
+        });
+        self.cover = Some(
+            world
+                .create_entity()
+                .with(cover)
+                .with(UiImage::SolidColor(cover_color(logo.opacity(0.))))
+                .build(),
+        );
     }
 
-    #[instrument(skip(_data), level = "info")]
-    fn fixed_update(&mut self, _data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        self.frame_displayed += 1;
-        // method is called every 1/60th seconds, 60 should be 1 seconds
-        if self.frame_displayed > 60 {
-            event!(Level::INFO, "Switching to MainMenu!");
-            Trans::Switch(Box::new(crate::menu::MainMenu::default()))
-        } else {
+    fn remove(&mut self, world: &mut World) {
+        for entity in self.ui_handle.take().into_iter().chain(self.cover.take()) {
+            world
+                .delete_entity(entity)
+                .expect("Failed to remove SplashScreen");
+        }
+    }
+
+    /// Display the next logo, or leave the splash screen if this was the last one.
+    fn next(&mut self, world: &mut World) -> SimpleTrans {
+        self.current += 1;
+        if self.current < self.config.logos.len() {
+            self.display(world);
             Trans::None
+        } else {
+            router::go(world, "done")
+        }
+    }
+
+    fn skip(&mut self, world: &mut World) -> SimpleTrans {
+        match self.config.logos.get(self.current).map(|logo| logo.skip) {
+            Some(SkipPolicy::Current) => self.next(world),
+            Some(SkipPolicy::All) | None => router::go(world, "done"),
+            Some(SkipPolicy::Unskippable) => Trans::None,
         }
     }
+}
+
+fn cover_color(opacity: f32) -> [f32; 4] {
+    [0.005, 0.005, 0.005, 1. - opacity]
+}
 
-    #[instrument(skip(_data, event), level = "info")]
+impl SimpleState for SplashScreen {
+    #[instrument(skip(data), level = "info")]
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.config = application_root_dir()
+            .map_err(amethyst::Error::from)
+            .and_then(|root| Ok(SplashConfig::load(root.join("config/splash.ron"))?))
+            .unwrap_or_else(|err| {
+                event!(Level::WARN, "Error loading splash config: {}", err);
+                SplashConfig::default()
+            });
+        self.current = 0;
+        self.display(data.world);
+    }
+
+    #[instrument(skip(data), level = "info")]
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let world = &mut *data.world;
+        let delta = world
+            .read_resource::<Time>()
+            .delta_seconds()
+            .min(MAX_FRAME_DURATION);
+        self.elapsed += delta;
+
+        let logo = match self.config.logos.get(self.current) {
+            Some(logo) => logo,
+            None => return router::go(world, "done"),
+        };
+        if self.elapsed >= logo.duration {
+            return self.next(world);
+        }
+        let opacity = logo.opacity(self.elapsed);
+        if let Some(cover) = self.cover {
+            if let Some(image) = world.write_storage::<UiImage>().get_mut(cover) {
+                *image = UiImage::SolidColor(cover_color(opacity));
+            }
+        }
+        Trans::None
+    }
+
+    #[instrument(skip(data, event), level = "info")]
     fn handle_event(
         &mut self,
-        _data: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
         match &event {
+            _ if console::is_typing(data.world, &event) => Trans::None,
             StateEvent::Window(event) => {
-                if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                if is_close_requested(&event) {
                     event!(Level::INFO, "[Trans::Quit] Quitting Application!");
                     Trans::Quit
                 } else if is_mouse_button_down(&event, MouseButton::Left) {
-                    event!(Level::INFO, "Switching to MainMenu!");
-                    Trans::Switch(Box::new(crate::menu::MainMenu::default()))
+                    self.skip(data.world)
                 } else {
                     Trans::None
                 }
             }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::BACK) => {
+                router::go(data.world, router::BACK)
+            }
+            StateEvent::Input(_) if bindings::is_action(&event, bindings::CONFIRM) => {
+                self.skip(data.world)
+            }
             _ => Trans::None,
         }
     }
 
     #[instrument(skip(data), level = "info")]
     fn on_stop(&mut self, data: StateData<GameData>) {
-        if let Some(root_entity) = self.ui_handle {
-            data.world
-                .delete_entity(root_entity)
-                .expect("Failed to remove SplashScreen");
-        }
-
-        self.ui_handle = None;
+        self.remove(data.world);
     }
 }
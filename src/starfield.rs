@@ -0,0 +1,262 @@
+use std::f32::consts::PI;
+
+use amethyst::{
+    core::{math::Vector3, Time, Transform},
+    ecs::prelude::{
+        Component, DenseVecStorage, Join, Read, ReadStorage, System, World, WorldExt, WriteStorage,
+    },
+    prelude::Builder,
+    renderer::{palette::Srgba, resources::Tint, SpriteRender},
+    window::ScreenDimensions,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    camera::CameraController,
+    gameplay::{GameplayState, TimeScale},
+    loading::Preloaded,
+};
+
+// part of the field outside of the view on each side, so stars don't pop in and out at the
+// edges
+const MARGIN: f32 = 0.05;
+
+/// A layer of stars. Sizes and speeds are relative to the height of the view, so the starfield
+/// looks the same at any resolution and zoom.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StarLayer {
+    /// Number of stars in a square the height of the view.
+    pub density: f32,
+    /// Height of the stars, relative to the view height.
+    pub size: f32,
+    /// Scrolling speed, in view heights per second.
+    pub speed: f32,
+    /// How much the layer follows the camera, from 0 for fixed on screen to 1 for moving with
+    /// the world.
+    pub parallax: f32,
+    /// How much the stars dim when twinkling, from 0 to 1.
+    pub twinkle: f32,
+    /// Twinkles per second.
+    pub twinkle_speed: f32,
+    /// Depth of the layer, the nearest layers should be higher.
+    pub depth: f32,
+}
+
+impl Default for StarLayer {
+    fn default() -> Self {
+        Self {
+            density: 50.,
+            size: 0.01,
+            speed: 0.02,
+            parallax: 0.2,
+            twinkle: 0.5,
+            twinkle_speed: 0.5,
+            depth: -1.,
+        }
+    }
+}
+
+/// The background starfield, from `config/starfield.ron`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StarfieldConfig {
+    /// Sprite sheet of the stars, as listed in the asset manifest.
+    pub sheet: String,
+    /// Direction the stars scroll to.
+    pub direction: (f32, f32),
+    /// From the farthest to the nearest.
+    pub layers: Vec<StarLayer>,
+}
+
+impl Default for StarfieldConfig {
+    fn default() -> Self {
+        Self {
+            sheet: "sprites/stars.ron".to_string(),
+            direction: (0., -1.),
+            layers: vec![],
+        }
+    }
+}
+
+/// A star of the starfield. Its position is relative to the view of the camera, between 0 and 1
+/// on both axis, and is turned into a `Transform` by the `StarfieldSystem`.
+#[derive(Debug, Clone)]
+pub struct Star {
+    layer: usize,
+    x: f32,
+    y: f32,
+    sprite_height: f32,
+    twinkle_phase: f32,
+}
+
+impl Component for Star {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Aspect ratio of the screen the stars were spawned for, their number depends on it.
+#[derive(Debug, Clone, Copy, Default)]
+struct SpawnedFor(f32);
+
+/// Fill the view with stars from the `StarfieldConfig`. The sprite sheet must have been
+/// loaded by the loading screen.
+pub fn spawn(world: &mut World) {
+    let config = world.read_resource::<StarfieldConfig>().clone();
+    let sheet = world
+        .try_fetch::<Preloaded>()
+        .and_then(|preloaded| preloaded.sprite_sheet(world, &config.sheet));
+    let (sheet, sprite_heights) = match sheet {
+        Some(sheet) if !sheet.1.is_empty() => sheet,
+        _ => {
+            event!(Level::WARN, "Star sheet {} is not loaded", config.sheet);
+            return;
+        }
+    };
+
+    let aspect_ratio = {
+        let screen = world.read_resource::<ScreenDimensions>();
+        screen.aspect_ratio()
+    };
+    world.insert(SpawnedFor(aspect_ratio));
+    let mut rng = rand::thread_rng();
+    for (layer_index, layer) in config.layers.iter().enumerate() {
+        let count = (layer.density * aspect_ratio).round() as usize;
+        event!(
+            Level::INFO,
+            "Spawning {} stars in layer {}",
+            count,
+            layer_index
+        );
+        for _ in 0..count {
+            let sprite_number = rng.gen_range(0, sprite_heights.len());
+            world
+                .create_entity()
+                .with(SpriteRender {
+                    sprite_sheet: sheet.clone(),
+                    sprite_number,
+                })
+                .with(Transform::default())
+                .with(Tint(Srgba::new(1., 1., 1., 1.)))
+                .with(Star {
+                    layer: layer_index,
+                    x: rng.gen(),
+                    y: rng.gen(),
+                    sprite_height: sprite_heights[sprite_number],
+                    twinkle_phase: rng.gen_range(0., 2. * PI),
+                })
+                .build();
+        }
+    }
+}
+
+/// Spawn the stars again when the screen changed shape since they were spawned, so the
+/// density stays the same.
+pub fn refill_if_resized(world: &mut World) {
+    let spawned_for = match world.try_fetch::<SpawnedFor>() {
+        Some(spawned_for) => spawned_for.0,
+        None => return,
+    };
+    let aspect_ratio = world.read_resource::<ScreenDimensions>().aspect_ratio();
+    if (aspect_ratio - spawned_for).abs() > f32::EPSILON {
+        event!(Level::INFO, "Screen resized, refilling the starfield");
+        clear(world);
+        spawn(world);
+    }
+}
+
+/// Remove every star.
+pub fn clear(world: &mut World) {
+    let stars = {
+        let entities = world.entities();
+        let stars = world.read_storage::<Star>();
+        (&entities, &stars)
+            .join()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>()
+    };
+    if let Err(err) = world.delete_entities(&stars) {
+        event!(Level::WARN, "Error removing stars: {}", err);
+    }
+    world.remove::<SpawnedFor>();
+}
+
+/// Scrolls the `Star`s and makes them twinkle while the gameplay runs. They wrap around the view
+/// of the camera, and their layer moves with the camera according to its parallax. It runs
+/// after the `CameraSystem`, so the stars are placed where the camera is this frame.
+#[derive(Debug, Default)]
+pub struct StarfieldSystem;
+
+impl<'a> System<'a> for StarfieldSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, TimeScale>,
+        Read<'a, GameplayState>,
+        Read<'a, StarfieldConfig>,
+        ReadStorage<'a, CameraController>,
+        WriteStorage<'a, Star>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Tint>,
+    );
+
+    #[instrument(
+        skip(time, time_scale, state, config, cameras, stars, transforms, tints),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            time,
+            time_scale,
+            state,
+            config,
+            cameras,
+            mut stars,
+            mut transforms,
+            mut tints,
+        ): Self::SystemData,
+    ) {
+        let delta = if *state == GameplayState::Running && !time_scale.stepping {
+            time_scale.delta(&time)
+        } else {
+            0.
+        };
+        let (camera_x, camera_y, width, height) = match (&cameras, &transforms).join().next() {
+            Some((camera, transform)) => (
+                transform.translation().x,
+                transform.translation().y,
+                camera.view.0,
+                camera.view.1,
+            ),
+            None => return,
+        };
+
+        for (star, transform, tint) in (&mut stars, &mut transforms, &mut tints).join() {
+            let layer = match config.layers.get(star.layer) {
+                Some(layer) => layer,
+                None => continue,
+            };
+            star.x =
+                (star.x + config.direction.0 * layer.speed * delta * height / width).rem_euclid(1.);
+            star.y = (star.y + config.direction.1 * layer.speed * delta).rem_euclid(1.);
+            star.twinkle_phase =
+                (star.twinkle_phase + layer.twinkle_speed * delta * 2. * PI).rem_euclid(2. * PI);
+
+            // the camera moved by (camera_x, camera_y), the layer moves back by its parallax
+            let x = (star.x - camera_x * layer.parallax / width).rem_euclid(1.);
+            let y = (star.y - camera_y * layer.parallax / height).rem_euclid(1.);
+            transform.set_translation_xyz(
+                camera_x + (x * (1. + 2. * MARGIN) - MARGIN - 0.5) * width,
+                camera_y + (y * (1. + 2. * MARGIN) - MARGIN - 0.5) * height,
+                layer.depth,
+            );
+            let scale = layer.size * height / star.sprite_height;
+            transform.set_scale(Vector3::new(scale, scale, 1.));
+
+            let brightness = 1. - layer.twinkle * 0.5 * (1. + star.twinkle_phase.sin());
+            tint.0 = Srgba::new(brightness, brightness, brightness, 1.);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    core::Time,
+    ecs::prelude::{Entities, Entity, Read, ReadExpect, System, Write, WriteStorage},
+    ui::{Anchor, FontAsset, TtfFormat, UiText, UiTransform},
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::{loading::Preloaded, ui_scheme::COLOR_3};
+
+// how long a toast stays on screen, in seconds
+const TOAST_DURATION: f32 = 3.;
+const TOAST_HEIGHT: f32 = 40.;
+const FONT: &str = "font/mandrill.ttf";
+
+/// Short messages to display at the bottom of the window for a few seconds. Anything can add
+/// one, whatever the current state.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    pending: Vec<String>,
+}
+
+impl Toasts {
+    pub fn show(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        event!(Level::INFO, "Toast: {}", text);
+        self.pending.push(text);
+    }
+}
+
+/// Displays the `Toasts`, stacked from the bottom of the window, and removes them when their
+/// time is up.
+#[derive(Debug, Default)]
+pub struct ToastSystem {
+    font: Option<Handle<FontAsset>>,
+    // displayed toasts, with their remaining time
+    displayed: Vec<(Entity, f32)>,
+}
+
+impl<'a> System<'a> for ToastSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Toasts>,
+        Read<'a, Time>,
+        WriteStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+        Option<Read<'a, Preloaded>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<FontAsset>>,
+    );
+
+    #[instrument(
+        skip(entities, toasts, time, transforms, texts, preloaded, loader, fonts),
+        level = "info"
+    )]
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut toasts,
+            time,
+            mut transforms,
+            mut texts,
+            preloaded,
+            loader,
+            fonts,
+        ): Self::SystemData,
+    ) {
+        let delta = time.delta_real_seconds();
+        self.displayed.retain(|(entity, remaining)| {
+            if *remaining > delta {
+                true
+            } else {
+                let _ = entities.delete(*entity);
+                false
+            }
+        });
+        for (_, remaining) in self.displayed.iter_mut() {
+            *remaining -= delta;
+        }
+
+        if !toasts.pending.is_empty() {
+            let font = self
+                .font
+                .get_or_insert_with(|| {
+                    preloaded
+                        .and_then(|preloaded| preloaded.fonts.get(FONT).cloned())
+                        .unwrap_or_else(|| loader.load(FONT, TtfFormat, (), &fonts))
+                })
+                .clone();
+            for text in toasts.pending.drain(..) {
+                let entity = entities
+                    .build_entity()
+                    .with(
+                        UiTransform::new(
+                            "toast".to_string(),
+                            Anchor::BottomMiddle,
+                            Anchor::BottomMiddle,
+                            0.,
+                            0.,
+                            50.,
+                            1000.,
+                            TOAST_HEIGHT,
+                        ),
+                        &mut transforms,
+                    )
+                    .with(UiText::new(font.clone(), text, COLOR_3, 30.), &mut texts)
+                    .build();
+                self.displayed.push((entity, TOAST_DURATION));
+            }
+        }
+
+        // newest at the bottom
+        for (i, (entity, _)) in self.displayed.iter().rev().enumerate() {
+            if let Some(transform) = transforms.get_mut(*entity) {
+                transform.local_y = 10. + i as f32 * TOAST_HEIGHT;
+            }
+        }
+    }
+}
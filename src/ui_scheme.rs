@@ -1,13 +1,30 @@
 use amethyst::{
     assets::{Asset, AssetStorage, Format, Handle, Loader},
+    config::Config,
     core::transform::Parent,
     ecs::{prelude::Entity, World},
     prelude::*,
-    ui::{Anchor, FontAsset, UiButtonActionRetrigger, UiImage, UiImageLoadPrefab, UiTransform},
+    ui::{
+        Anchor, FontAsset, Stretch, UiButtonActionRetrigger, UiCreator, UiImage, UiImageLoadPrefab,
+        UiTransform,
+    },
+    utils::application_root_dir,
 };
 
+use serde::{Deserialize, Serialize};
 use tracing::{event, instrument, Level};
 
+use crate::{
+    action::{find_action, UiAction},
+    audio::UiSounds,
+    console::{ArgKind, Commands, Param},
+    focus::{focus, Focusable},
+    i18n::Translations,
+    loading::Preloaded,
+    settings::UserSettings,
+    widget::GameWidget,
+};
+
 pub const COLOR_BORDER: [f32; 4] = [0.78, 0.933, 0.106, 1.];
 pub const COLOR_TEXT_LIGHT: [f32; 4] = [0.282, 0.624, 0.71, 1.];
 pub const COLOR_3: [f32; 4] = [0.569, 0.961, 0.678, 1.];
@@ -15,6 +32,68 @@ pub const COLOR_ACTING: [f32; 4] = [0.988, 0.624, 0.357, 1.];
 pub const COLOR_BACKGROUND_HIGHLIGHTED: [f32; 4] = [0.329, 0.071, 0.094, 1.];
 pub const COLOR_BACKGROUND: [f32; 4] = [0.196, 0.043, 0.055, 1.];
 
+/// Look and feel of the UI, loaded from `assets/themes/<name>.ron`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Theme {
+    pub sounds: ThemeSounds,
+}
+
+/// Paths to the sounds played on UI interactions, relative to the assets folder.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ThemeSounds {
+    pub hover: Option<String>,
+    pub click: Option<String>,
+}
+
+impl Default for ThemeSounds {
+    fn default() -> Self {
+        Self {
+            hover: Some("audio/boop.ogg".to_string()),
+            click: Some("audio/confirm.ogg".to_string()),
+        }
+    }
+}
+
+/// Console command to use another theme from `assets/themes`. Screens pick it up when they are
+/// created.
+pub fn register_commands(world: &World) {
+    let themes_dir = |root: &std::path::Path| root.join("assets/themes");
+    let mut themes = application_root_dir()
+        .ok()
+        .and_then(|root| std::fs::read_dir(themes_dir(&root)).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    if path.extension()? == "ron" {
+                        Some(path.file_stem()?.to_string_lossy().to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    themes.sort();
+
+    world.write_resource::<Commands>().register(
+        "theme",
+        "Use another theme",
+        vec![Param::new("name", ArgKind::Choice(themes))],
+        move |world, args| {
+            let name = args.text(0).unwrap_or_default();
+            let theme =
+                Theme::load(themes_dir(&application_root_dir()?).join(format!("{}.ron", name)))?;
+            let sounds = UiSounds::from_theme(world, &theme);
+            world.insert(sounds);
+            world.insert(theme);
+            Ok(format!("Theme: {}", name))
+        },
+    );
+}
+
 pub trait Color {
     fn as_solid_color(&self) -> UiImageLoadPrefab;
 }
@@ -25,12 +104,38 @@ impl Color for [f32; 4] {
     }
 }
 
+// assets are taken from the ones loaded by the loading screen when possible
+
 pub fn load_font(world: &World, name: &str) -> Handle<FontAsset> {
-    load(world, name, amethyst::ui::TtfFormat)
+    world
+        .try_fetch::<Preloaded>()
+        .and_then(|preloaded| preloaded.fonts.get(name).cloned())
+        .unwrap_or_else(|| load(world, name, amethyst::ui::TtfFormat))
 }
 
 pub fn load_image(world: &World, name: &str) -> Handle<amethyst::renderer::Texture> {
-    load(world, name, amethyst::renderer::ImageFormat::default())
+    world
+        .try_fetch::<Preloaded>()
+        .and_then(|preloaded| preloaded.textures.get(name).cloned())
+        .unwrap_or_else(|| load(world, name, amethyst::renderer::ImageFormat::default()))
+}
+
+pub fn load_sound(world: &World, name: &str) -> amethyst::audio::SourceHandle {
+    world
+        .try_fetch::<Preloaded>()
+        .and_then(|preloaded| preloaded.sounds.get(name).cloned())
+        .unwrap_or_else(|| load(world, name, amethyst::audio::OggFormat))
+}
+
+/// Create the UI of a prefab, relative to the assets folder.
+pub fn create_prefab(world: &mut World, name: &str) -> Entity {
+    let preloaded = world
+        .try_fetch::<Preloaded>()
+        .and_then(|preloaded| preloaded.ui.get(name).cloned());
+    match preloaded {
+        Some(prefab) => world.create_entity().with(prefab).build(),
+        None => world.exec(|mut creator: UiCreator<'_, GameWidget>| creator.create(name, ())),
+    }
 }
 
 fn load<A: Asset<Data = D>, D: 'static + Send + Sync, F: Format<D>>(
@@ -54,12 +159,19 @@ pub struct Button {
     pub height: f32,
     pub border: f32,
     pub font: Option<Handle<FontAsset>>,
+    pub font_size: f32,
+    pub focus_order: u32,
+    pub action: Option<UiAction>,
 }
 
-#[derive(Default)]
-pub struct ButtonComp;
+/// Set on the entity catching the events of a button, linking it to the background entity
+/// that should be highlighted.
+#[derive(Debug, Clone, Copy)]
+pub struct ButtonComp {
+    pub background: Entity,
+}
 impl amethyst::ecs::Component for ButtonComp {
-    type Storage = amethyst::ecs::NullStorage<ButtonComp>;
+    type Storage = amethyst::ecs::DenseVecStorage<ButtonComp>;
 }
 
 impl Button {
@@ -71,12 +183,19 @@ impl Button {
             height: 150.,
             border: 5.,
             font: None,
+            font_size: 70.,
+            focus_order: 0,
+            action: None,
         }
     }
 
     #[instrument(skip(world), level = "info")]
     pub fn create(self, world: &mut World, parent: Entity, transform: UiTransform) -> Entity {
         let font = load_font(world, "font/mandrill.ttf");
+        let label = match world.try_fetch::<Translations>() {
+            Some(translations) => translations.tr(&self.text).to_string(),
+            None => self.text,
+        };
 
         let border = UiImage::SolidColor(COLOR_BORDER);
         let border_transform = UiTransform::new(
@@ -121,7 +240,7 @@ impl Button {
             self.height - self.border,
         );
 
-        let text = amethyst::ui::UiText::new(font.clone(), self.text, COLOR_TEXT_LIGHT, 70.);
+        let text = amethyst::ui::UiText::new(font.clone(), label, COLOR_TEXT_LIGHT, self.font_size);
         let text_transform = UiTransform::new(
             format!("{}_text", self.id),
             Anchor::Middle,
@@ -171,11 +290,10 @@ impl Button {
             })
             .build();
 
-        world
+        let background_entity = world
             .create_entity()
             .with(background)
             .with(background_transform)
-            .with(ButtonComp)
             .with(UiButtonActionRetrigger {
                 on_click_start: vec![],
                 on_click_stop: vec![],
@@ -190,12 +308,286 @@ impl Button {
             .with(text_transform)
             .with(Parent { entity: handle })
             .build();
-        world
+        let mut catch = world
             .create_entity()
             .with(catch_transform)
+            .with(ButtonComp {
+                background: background_entity,
+            })
+            .with(Focusable::new(self.focus_order))
+            .with(Parent { entity: handle });
+        if let Some(action) = self.action {
+            catch = catch.with(action);
+        }
+        catch.build();
+
+        handle
+    }
+}
+
+/// A vertical list of buttons, each triggering an `UiAction`. Buttons are sized according to
+/// the UI scale of the `UserSettings`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Menu {
+    pub id: String,
+    /// Color of a background covering the whole window, if any.
+    #[serde(default)]
+    pub background: Option<[f32; 4]>,
+    #[serde(default = "default_button_size")]
+    pub button_size: (f32, f32),
+    pub buttons: Vec<MenuButton>,
+}
+
+fn default_button_size() -> (f32, f32) {
+    (800., 150.)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MenuButton {
+    pub text: String,
+    pub id: String,
+    pub action: UiAction,
+}
+
+impl MenuButton {
+    pub fn new(text: &str, id: &str, action: UiAction) -> MenuButton {
+        MenuButton {
+            text: text.to_string(),
+            id: id.to_string(),
+            action,
+        }
+    }
+}
+
+impl Menu {
+    pub fn new(id: &str, buttons: Vec<MenuButton>) -> Menu {
+        Menu {
+            id: id.to_string(),
+            background: None,
+            button_size: default_button_size(),
+            buttons,
+        }
+    }
+
+    #[instrument(skip(world), level = "info")]
+    pub fn create(self, world: &mut World) -> Entity {
+        let root = world.create_entity();
+        let root = match self.background {
+            Some(color) => root
+                .with(
+                    UiTransform::new(
+                        self.id.clone(),
+                        Anchor::Middle,
+                        Anchor::Middle,
+                        0.,
+                        0.,
+                        2.,
+                        20.,
+                        20.,
+                    )
+                    .with_stretch(Stretch::XY {
+                        x_margin: 0.,
+                        y_margin: 0.,
+                        keep_aspect_ratio: false,
+                    }),
+                )
+                .with(UiImage::SolidColor(color)),
+            None => root.with(UiTransform::new(
+                self.id.clone(),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.,
+                0.,
+                0.,
+                0.,
+                0.,
+            )),
+        };
+        let menu = root.build();
+
+        let count = self.buttons.len();
+        let scale = world
+            .try_fetch::<UserSettings>()
+            .map(|settings| settings.ui_scale)
+            .unwrap_or(1.);
+        let (width, height) = (self.button_size.0 * scale, self.button_size.1 * scale);
+        self.buttons
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, button)| {
+                let transform = UiTransform::new(
+                    format!("{}_container", button.id),
+                    Anchor::Middle,
+                    Anchor::Middle,
+                    0.,
+                    ((count as f32) / 2. - 0.5) * height - (i as f32) * (height + 10.),
+                    0.,
+                    0.,
+                    0.,
+                );
+                Button {
+                    width,
+                    height,
+                    font_size: 70. * height / 150.,
+                    focus_order: i as u32,
+                    action: Some(button.action),
+                    ..Button::new(&button.text, &button.id)
+                }
+                .create(world, menu, transform);
+            });
+
+        menu
+    }
+}
+
+/// A `Menu` with a notice at the top of the window, for screens rebuilt each time what they
+/// display changes, like the settings or the bindings.
+#[derive(Debug, Default)]
+pub struct MenuScreen {
+    root: Option<Entity>,
+    notice: Option<Entity>,
+}
+
+impl MenuScreen {
+    /// Replace the displayed menu and notice, then focus the button triggering `focused`.
+    pub fn show(&mut self, world: &mut World, menu: Menu, notice: &str, focused: &UiAction) {
+        self.delete(world);
+
+        let notice_id = format!("{}_notice", menu.id);
+        self.root = Some(menu.create(world));
+
+        let font = load_font(world, "font/mandrill.ttf");
+        let notice = match world.try_fetch::<Translations>() {
+            Some(translations) => translations.tr(notice).to_string(),
+            None => notice.to_string(),
+        };
+        self.notice = Some(
+            world
+                .create_entity()
+                .with(UiTransform::new(
+                    notice_id,
+                    Anchor::TopMiddle,
+                    Anchor::TopMiddle,
+                    0.,
+                    -10.,
+                    1.,
+                    1000.,
+                    40.,
+                ))
+                .with(amethyst::ui::UiText::new(font, notice, COLOR_3, 30.))
+                .build(),
+        );
+
+        if let Some(entity) = find_action(world, focused) {
+            focus(world, entity);
+        }
+    }
+
+    pub fn delete(&mut self, world: &mut World) {
+        for entity in self.root.take().into_iter().chain(self.notice.take()) {
+            world
+                .delete_entity(entity)
+                .expect("Failed to remove menu screen");
+        }
+    }
+}
+
+/// An horizontal bar, filled from the left depending on progress.
+#[derive(Debug)]
+pub struct ProgressBar {
+    pub id: String,
+    pub width: f32,
+    pub height: f32,
+    pub border: f32,
+}
+
+impl ProgressBar {
+    pub fn new(id: &str) -> ProgressBar {
+        ProgressBar {
+            id: id.to_string(),
+            width: 800.,
+            height: 60.,
+            border: 5.,
+        }
+    }
+
+    #[instrument(skip(world), level = "info")]
+    pub fn create(
+        self,
+        world: &mut World,
+        parent: Entity,
+        transform: UiTransform,
+    ) -> ProgressBarFill {
+        let handle = world
+            .create_entity()
+            .with(transform)
+            .with(Parent { entity: parent })
+            .build();
+        world
+            .create_entity()
+            .with(UiImage::SolidColor(COLOR_BORDER))
+            .with(UiTransform::new(
+                format!("{}_border", self.id),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.,
+                0.,
+                0.,
+                self.width,
+                self.height,
+            ))
+            .with(Parent { entity: handle })
+            .build();
+        world
+            .create_entity()
+            .with(UiImage::SolidColor(COLOR_BACKGROUND))
+            .with(UiTransform::new(
+                format!("{}_background", self.id),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.,
+                0.,
+                0.1,
+                self.width - self.border,
+                self.height - self.border,
+            ))
+            .with(Parent { entity: handle })
+            .build();
+        let fill = world
+            .create_entity()
+            .with(UiImage::SolidColor(COLOR_ACTING))
+            .with(UiTransform::new(
+                format!("{}_fill", self.id),
+                Anchor::MiddleLeft,
+                Anchor::MiddleLeft,
+                self.border / 2.,
+                0.,
+                0.2,
+                0.,
+                self.height - self.border,
+            ))
             .with(Parent { entity: handle })
             .build();
 
-        handle
+        ProgressBarFill {
+            entity: fill,
+            max_width: self.width - self.border,
+        }
+    }
+}
+
+/// The filling part of a `ProgressBar`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressBarFill {
+    pub entity: Entity,
+    pub max_width: f32,
+}
+
+impl ProgressBarFill {
+    /// Fill the bar to `ratio`, between 0 and 1.
+    pub fn set(&self, world: &World, ratio: f32) {
+        if let Some(transform) = world.write_storage::<UiTransform>().get_mut(self.entity) {
+            transform.width = self.max_width * ratio.max(0.).min(1.);
+        }
     }
 }
@@ -0,0 +1,60 @@
+use amethyst::ui::{ToNativeWidget, UiPrefab, UiWidget};
+
+use serde::Deserialize;
+
+use crate::{action::UiAction, focus::Focusable, link::Link};
+
+/// Custom widgets of the UI prefabs, on top of the ones of amethyst.
+#[derive(Debug, Clone, Deserialize)]
+pub enum GameWidget {
+    /// A widget that can be reached with the keyboard or a gamepad, visited in the order of the
+    /// `tab_order` of its transform:
+    /// `Custom(Focusable(Button(transform: (tab_order: 1, ..), button: (..))))`
+    Focusable(UiWidget<GameWidget>),
+    /// A focusable widget triggering an `UiAction` when activated:
+    /// `Custom(Action(action: Resume, widget: Button(..)))`
+    Action {
+        action: UiAction,
+        widget: UiWidget<GameWidget>,
+    },
+    /// A focusable text opening `url` when activated, if the `LinkPolicy` allows it:
+    /// `Custom(Link(url: "https://amethyst.rs", widget: Label(..)))`
+    Link {
+        url: String,
+        widget: UiWidget<GameWidget>,
+    },
+}
+
+fn tab_order(widget: &UiWidget<GameWidget>) -> u32 {
+    match widget {
+        UiWidget::Container { transform, .. }
+        | UiWidget::Image { transform, .. }
+        | UiWidget::Label { transform, .. }
+        | UiWidget::Button { transform, .. } => transform.tab_order,
+        _ => 0,
+    }
+}
+
+impl ToNativeWidget for GameWidget {
+    type PrefabData = (Option<Focusable>, Option<UiAction>, Option<Link>);
+
+    fn to_native_widget(self, _: ()) -> (UiWidget<GameWidget>, Self::PrefabData) {
+        match self {
+            GameWidget::Focusable(widget) => {
+                let focusable = Focusable::new(tab_order(&widget));
+                (widget, (Some(focusable), None, None))
+            }
+            GameWidget::Action { action, widget } => {
+                let focusable = Focusable::new(tab_order(&widget));
+                (widget, (Some(focusable), Some(action), None))
+            }
+            GameWidget::Link { url, widget } => {
+                let focusable = Focusable::new(tab_order(&widget));
+                (widget, (Some(focusable), None, Some(Link { url })))
+            }
+        }
+    }
+}
+
+/// Prefabs of the UI, made of amethyst widgets and `GameWidget`s.
+pub type GameUiPrefab = UiPrefab<<GameWidget as ToNativeWidget>::PrefabData>;
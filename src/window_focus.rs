@@ -0,0 +1,108 @@
+use amethyst::{
+    core::frame_limiter::FrameLimiter,
+    derive::SystemDesc,
+    ecs::prelude::{Read, System, SystemData, Write},
+    shrev::{EventChannel, ReaderId},
+    winit::{Event, WindowEvent},
+};
+
+use tracing::{event, instrument, Level};
+
+use crate::settings::{limit_frame_rate, UserSettings};
+
+/// State of the window, kept up to date by the `WindowFocusSystem`.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowFocus {
+    pub focused: bool,
+    pub minimized: bool,
+    /// Sounds should not be played.
+    pub muted: bool,
+}
+
+impl Default for WindowFocus {
+    fn default() -> Self {
+        Self {
+            focused: true,
+            minimized: false,
+            muted: false,
+        }
+    }
+}
+
+impl WindowFocus {
+    /// The player is not looking at the game.
+    pub fn is_away(&self) -> bool {
+        !self.focused || self.minimized
+    }
+}
+
+/// Is this event the window losing focus or being minimized.
+pub fn is_focus_lost(event: &Event) -> bool {
+    match event {
+        Event::WindowEvent {
+            event: WindowEvent::Focused(false),
+            ..
+        } => true,
+        Event::WindowEvent {
+            event: WindowEvent::Resized(size),
+            ..
+        } => is_minimized_size(size.width, size.height),
+        _ => false,
+    }
+}
+
+// there is no minimize event, a minimized window gets resized to nothing
+fn is_minimized_size(width: f64, height: f64) -> bool {
+    width <= 0. || height <= 0.
+}
+
+/// Tracks the `WindowFocus`, and applies the `UnfocusedSettings` when the player is away: mutes
+/// sounds and lowers the frame rate. Pausing is done by the game state.
+#[derive(SystemDesc, Debug)]
+#[system_desc(name(WindowFocusSystemDesc))]
+pub struct WindowFocusSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<Event>,
+}
+
+impl WindowFocusSystem {
+    pub fn new(reader_id: ReaderId<Event>) -> Self {
+        Self { reader_id }
+    }
+}
+
+impl<'a> System<'a> for WindowFocusSystem {
+    type SystemData = (
+        Read<'a, EventChannel<Event>>,
+        Write<'a, WindowFocus>,
+        Read<'a, UserSettings>,
+        Write<'a, FrameLimiter>,
+    );
+
+    #[instrument(skip(events, focus, settings, frame_limiter), level = "info")]
+    fn run(&mut self, (events, mut focus, settings, mut frame_limiter): Self::SystemData) {
+        let was_away = focus.is_away();
+        for ev in events.read(&mut self.reader_id) {
+            if let Event::WindowEvent { event, .. } = ev {
+                match event {
+                    WindowEvent::Focused(focused) => focus.focused = *focused,
+                    WindowEvent::Resized(size) => {
+                        focus.minimized = is_minimized_size(size.width, size.height)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        if focus.is_away() == was_away {
+            return;
+        }
+
+        let away = focus.is_away();
+        event!(Level::INFO, "Player away: {}", away);
+        focus.muted = away && settings.unfocused.mute;
+        *frame_limiter = match settings.unfocused.frame_rate {
+            Some(fps) if away => limit_frame_rate(fps),
+            _ => settings.display.frame_limiter(),
+        };
+    }
+}